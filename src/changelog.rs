@@ -0,0 +1,1111 @@
+/// Version page changelog handling
+///
+/// A version page lists its changes under a `# Changed Pages` heading, with
+/// one entry per version, newest first:
+///
+/// ```text
+/// - # Changed Pages
+///     - ## [[1.2.3]]
+///         - ### Added
+///             - [[Page]]
+/// ```
+///
+/// When an entry lists more pages than `max_changed_pages` allows, its
+/// Added, Modified and Deleted subsections move to an overflow page titled
+/// `<version>/Changes`, which has a Changed Pages section of its own in the
+/// same format. The entry on the version page then links to it instead:
+///
+/// ```text
+/// - # Changed Pages
+///     - ## [[1.2.3]]
+///         - ### Changes
+///             - 120 added, 30 modified, 2 deleted, listed in [[1.2.3/Changes]]
+/// ```
+///
+/// Once an overflow page exists, later syncs of the version record their
+/// changes there and refresh the link.
+///
+/// Pages may also be listed with a display name in Logseq's labelled link
+/// form, `[Amoxicillin]([[Drug/Antibiotics/Amoxicillin]])`, which is read
+/// back as the linked page.
+///
+/// The Summary, Changed Pages, Added, Modified and Deleted headings can be
+/// renamed in the configuration, so every function locating them takes the
+/// configured [`ChangelogHeadings`].
+use anyhow::Result;
+use semver::Version;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Range,
+};
+
+use crate::config::{ChangelogHeadings, ChangelogOrder};
+use crate::file_manager::{block_text, ChangedPages, LogseqPage};
+
+/// Subsection of a version entry linking to its overflow page
+const OVERFLOW_SECTION: &str = "### Changes";
+
+/// Suffix of the title of an overflow page, after the version page title
+const OVERFLOW_SUFFIX: &str = "/Changes";
+
+/// Get the blocks of the Summary section of a version page
+///
+/// Returns `None` if the page has no Summary heading.
+pub fn summary(page: &LogseqPage, headings: &ChangelogHeadings) -> Option<Vec<(String, u8)>> {
+    section_body(&page.contents, &headings.summary).map(|range| page.contents[range].to_vec())
+}
+
+/// Replace the blocks of the Summary section of a version page
+///
+/// The rest of the page, including the blank lines ending the section, is
+/// left untouched. Does nothing if the page has no Summary heading.
+pub fn replace_summary(
+    page: &mut LogseqPage,
+    blocks: Vec<(String, u8)>,
+    headings: &ChangelogHeadings,
+) {
+    if let Some(range) = section_body(&page.contents, &headings.summary) {
+        page.contents.splice(range, blocks);
+    }
+}
+
+/// Get the range of the blocks under a top-level heading
+///
+/// The section ends at the next top-level block. Trailing blank lines are
+/// not part of the range.
+fn section_body(contents: &[(String, u8)], heading: &str) -> Option<Range<usize>> {
+    let heading_index = contents
+        .iter()
+        .position(|(line, indent)| block_text(line) == heading && *indent == 0)?;
+    Some(section_body_at(contents, heading_index))
+}
+
+/// Get the range of the blocks under the top-level heading at `heading_index`
+fn section_body_at(contents: &[(String, u8)], heading_index: usize) -> Range<usize> {
+    let start = heading_index + 1;
+    let end = contents[start..]
+        .iter()
+        .position(|(line, indent)| *indent == 0 && !line.is_empty())
+        .map_or(contents.len(), |pos| pos + start);
+    let end = contents[start..end]
+        .iter()
+        .rposition(|(line, _)| !line.is_empty())
+        .map_or(start, |pos| pos + start + 1);
+    start..end
+}
+
+/// Add a line under the Summary heading of a version page
+pub fn add_summary(
+    page: &mut LogseqPage,
+    summary: &str,
+    headings: &ChangelogHeadings,
+) -> Result<()> {
+    page.insert_under_heading(&headings.summary, (summary.to_string(), 1))
+}
+
+/// Get the title of the page holding the changelog of a version
+///
+/// Pre-releases get pages of their own, e.g. `1.2.3-rc.1`. Build metadata is
+/// dropped, as it doesn't make a different version.
+pub fn version_page_title(version: &Version) -> String {
+    let mut title = format!("{}.{}.{}", version.major, version.minor, version.patch);
+    if !version.pre.is_empty() {
+        title += &format!("-{}", version.pre);
+    }
+    title
+}
+
+/// Get the title of the overflow page of a version
+pub fn overflow_page_title(version: &Version) -> String {
+    version_page_title(version) + OVERFLOW_SUFFIX
+}
+
+/// Get the version of an overflow page from its title
+///
+/// Returns `None` if the title isn't that of an overflow page.
+pub fn overflow_page_version(title: &str) -> Option<Version> {
+    Version::parse(title.strip_suffix(OVERFLOW_SUFFIX)?).ok()
+}
+
+/// Build the changelog entry of a version from changed pages
+///
+/// Empty subsections are omitted and pages are sorted within each subsection
+/// in the given order.
+pub fn version_entry(
+    version: &Version,
+    changed_pages: &ChangedPages,
+    headings: &ChangelogHeadings,
+    order: ChangelogOrder,
+) -> Vec<(String, u8)> {
+    annotated_entry(version, changed_pages, headings, order, &HashMap::new())
+}
+
+/// What a version entry records along with a listed page, besides its link
+#[derive(Debug, Default)]
+struct PageNotes {
+    /// Block properties of the line listing the page, each after a newline
+    properties: String,
+    /// Blocks after the line listing the page, up to the next listed page,
+    /// such as notes nested under it
+    blocks: Vec<(String, u8)>,
+}
+
+/// Notes of the pages listed in a version entry, by subsection index in
+/// [`ChangelogHeadings::change_sections`] and page title
+///
+/// Blocks of a subsection before its first listed page are kept under an
+/// empty title.
+type EntryNotes = HashMap<(usize, String), PageNotes>;
+
+/// Build the changelog entry of a version like `version_entry`, writing
+/// back the notes recorded with each page
+fn annotated_entry(
+    version: &Version,
+    changed_pages: &ChangedPages,
+    headings: &ChangelogHeadings,
+    order: ChangelogOrder,
+    notes: &EntryNotes,
+) -> Vec<(String, u8)> {
+    let mut entry = vec![(version_heading(version), 1)];
+    let sections = [
+        &changed_pages.added,
+        &changed_pages.modified,
+        &changed_pages.deleted,
+    ];
+    for (i, (section, pages)) in headings.change_sections().iter().zip(sections).enumerate() {
+        if pages.is_empty() {
+            continue;
+        }
+        let mut pages = pages.clone();
+        sort_pages(&mut pages, order);
+        pages.dedup();
+        entry.push((section.to_string(), 2));
+        if let Some(leading) = notes.get(&(i, String::new())) {
+            entry.extend(leading.blocks.iter().cloned());
+        }
+        for page in pages {
+            let link = page_link(&page, None);
+            match notes.get(&(i, page)) {
+                Some(page_notes) => {
+                    entry.push((link + &page_notes.properties, 3));
+                    entry.extend(page_notes.blocks.iter().cloned());
+                }
+                None => entry.push((link, 3)),
+            }
+        }
+    }
+    entry
+}
+
+/// Collect the notes of the pages listed in the body of a version entry
+fn entry_notes(body: &[(String, u8)], headings: &ChangelogHeadings) -> EntryNotes {
+    let mut notes = EntryNotes::new();
+    let mut current = None;
+    for (line, indent) in body {
+        if *indent <= 2 {
+            current = change_section(line, *indent, headings).map(|i| (i, String::new()));
+            continue;
+        }
+        let Some((section, _)) = current else {
+            continue;
+        };
+        if let (Some(page), 3) = (listed_page(line), *indent) {
+            let key = (section, page.to_string());
+            notes
+                .entry(key.clone())
+                .or_default()
+                .properties
+                .push_str(&line[block_text(line).len()..]);
+            current = Some(key);
+            continue;
+        }
+        if let Some(key) = &current {
+            notes
+                .entry(key.clone())
+                .or_default()
+                .blocks
+                .push((line.clone(), *indent));
+        }
+    }
+    notes
+}
+
+/// Sort page titles in the given order
+///
+/// In namespace order, titles are compared by their namespace segments, with
+/// pages outside any namespace first, and then by their last segment.
+fn sort_pages(pages: &mut [String], order: ChangelogOrder) {
+    match order {
+        ChangelogOrder::Title => pages.sort(),
+        ChangelogOrder::Namespace => pages.sort_by_cached_key(|title| {
+            let mut segments = title.split('/').map(String::from).collect::<Vec<_>>();
+            let leaf = segments.pop();
+            (segments, leaf)
+        }),
+    }
+}
+
+/// Get the page listed by a line of a change subsection
+///
+/// The line is either a link, `[[Title]]`, or a labelled link,
+/// `[Display]([[Title]])`.
+fn listed_page(line: &str) -> Option<&str> {
+    let text = block_text(line);
+    let link = match text.strip_prefix('[').filter(|_| !text.starts_with("[[")) {
+        Some(labelled) => labelled.split_once("](")?.1.strip_suffix(')')?,
+        None => text,
+    };
+    link.strip_prefix("[[")?.strip_suffix("]]")
+}
+
+/// Format the line listing a page, labelled with `display` if given
+pub fn page_link(title: &str, display: Option<&str>) -> String {
+    match display {
+        Some(display) if display != title => format!("[{}]([[{}]])", display, title),
+        _ => format!("[[{}]]", title),
+    }
+}
+
+/// Relabel the pages listed in changelog entries with display names
+///
+/// Pages under the Added, Modified and Deleted subsections of the latest
+/// entry, if it is for `version`, or of all entries if `version` is `None`,
+/// are listed as `[Display]([[Title]])` where `display` gives a name, and as
+/// `[[Title]]` otherwise. Block properties of the lines are kept.
+pub fn label_links(
+    page: &LogseqPage,
+    version: Option<&Version>,
+    headings: &ChangelogHeadings,
+    display: impl Fn(&str) -> Option<String>,
+) -> LogseqPage {
+    let range = match version {
+        Some(version) => {
+            let entry = page
+                .contents
+                .iter()
+                .position(|line| is_changed_pages_heading(line, headings))
+                .and_then(|heading| latest_entry_range(&page.contents, heading, version));
+            match entry {
+                Some(range) => range,
+                None => return page.clone(),
+            }
+        }
+        None => 0..page.contents.len(),
+    };
+
+    let mut contents = page.contents.clone();
+    let mut section = None;
+    for (line, indent) in &mut contents[range] {
+        if *indent <= 2 {
+            section = change_section(line, *indent, headings);
+        }
+        if section.is_none() || *indent != 3 {
+            continue;
+        }
+        if let Some(title) = listed_page(line).map(str::to_string) {
+            let properties = line[block_text(line).len()..].to_string();
+            *line = page_link(&title, display(&title).as_deref()) + &properties;
+        }
+    }
+    LogseqPage::new(&page.title, page.properties.clone(), contents)
+}
+
+/// Merge changed pages into the changelog of a version page
+///
+/// If the latest entry in the Changed Pages section is for `version`, its
+/// pages are merged with `changes` and any custom subsections (e.g.
+/// "### Notes") are carried forward after the generated ones. Blocks nested
+/// under a listed page and its block properties stay with the page. Otherwise a
+/// new entry is added above the existing ones. A missing Changed Pages
+/// heading is appended to the page.
+pub fn merge_changed_pages(
+    existing: &LogseqPage,
+    changes: &ChangedPages,
+    version: &Version,
+    headings: &ChangelogHeadings,
+    order: ChangelogOrder,
+) -> LogseqPage {
+    update_entry(existing, version, headings, |body| {
+        let mut merged = changes.clone();
+        let mut custom_sections = Vec::new();
+        let mut notes = EntryNotes::new();
+        if let Some(body) = body {
+            let (existing_changes, custom) = parse_entry(body, headings);
+            merged.added.extend(existing_changes.added);
+            merged.modified.extend(existing_changes.modified);
+            merged.deleted.extend(existing_changes.deleted);
+            custom_sections = custom;
+            notes = entry_notes(body, headings);
+        }
+
+        let mut entry = annotated_entry(version, &merged, headings, order, &notes);
+        entry.extend(custom_sections);
+        entry
+    })
+}
+
+/// Replace the changelog entry of a version solely with `changes`
+///
+/// Like `merge_changed_pages`, but discards everything previously recorded
+/// in the latest entry if it is for `version`.
+pub fn recreate_changed_pages(
+    existing: &LogseqPage,
+    changes: &ChangedPages,
+    version: &Version,
+    headings: &ChangelogHeadings,
+    order: ChangelogOrder,
+) -> LogseqPage {
+    update_entry(existing, version, headings, |_| {
+        version_entry(version, changes, headings, order)
+    })
+}
+
+/// Replace or insert the entry of a version in the Changed Pages section
+///
+/// The Changed Pages heading is appended if the page doesn't have one.
+/// `build` receives the body of the latest entry if it is for `version`, and
+/// returns the entry to put in its place at the top of the section.
+fn update_entry(
+    existing: &LogseqPage,
+    version: &Version,
+    headings: &ChangelogHeadings,
+    build: impl FnOnce(Option<&[(String, u8)]>) -> Vec<(String, u8)>,
+) -> LogseqPage {
+    let mut contents = existing.contents.clone();
+    let changed_pages_index = changed_pages_heading(&mut contents, headings);
+    let latest_entry = latest_entry_range(&contents, changed_pages_index, version);
+
+    let entry = build(
+        latest_entry
+            .clone()
+            .map(|range| &contents[range.start + 1..range.end]),
+    );
+    // Remove the existing entry as it is replaced by the new one
+    if let Some(range) = latest_entry {
+        contents.drain(range);
+    }
+    let insert_position = changed_pages_index + 1;
+    contents.splice(insert_position..insert_position, entry);
+
+    LogseqPage::new(&existing.title, existing.properties.clone(), contents)
+}
+
+/// Get the pages listed in the latest changelog entry if it is for `version`
+pub fn entry_changes(
+    page: &LogseqPage,
+    version: &Version,
+    headings: &ChangelogHeadings,
+) -> Option<ChangedPages> {
+    current_entry(page, version, headings).map(|entry| parse_entry(&entry[1..], headings).0)
+}
+
+/// Replace the pages listed in the latest entry with a link to the overflow
+/// page of `version`
+///
+/// `overflow` holds the pages recorded on the overflow page, which are
+/// counted in the link. Custom subsections of the entry are kept.
+pub fn link_overflow(
+    page: &LogseqPage,
+    version: &Version,
+    overflow: &ChangedPages,
+    headings: &ChangelogHeadings,
+) -> LogseqPage {
+    update_entry(page, version, headings, |body| {
+        let mut entry = vec![
+            (version_heading(version), 1),
+            (OVERFLOW_SECTION.to_string(), 2),
+            (
+                format!(
+                    "{}, listed in [[{}]]",
+                    overflow.summary(),
+                    overflow_page_title(version)
+                ),
+                3,
+            ),
+        ];
+        let custom_sections = body
+            .map(|body| parse_entry(body, headings).1)
+            .unwrap_or_default();
+        let mut in_overflow_section = false;
+        for (line, indent) in custom_sections {
+            if indent <= 2 {
+                in_overflow_section = indent == 2 && block_text(&line) == OVERFLOW_SECTION;
+            }
+            if !in_overflow_section {
+                entry.push((line, indent));
+            }
+        }
+        entry
+    })
+}
+
+/// Get the latest changelog entry of a version page if it is for `version`
+///
+/// The entry starts with its `## [[version]]` heading.
+pub fn current_entry<'a>(
+    page: &'a LogseqPage,
+    version: &Version,
+    headings: &ChangelogHeadings,
+) -> Option<&'a [(String, u8)]> {
+    let heading_index = page
+        .contents
+        .iter()
+        .position(|line| is_changed_pages_heading(line, headings))?;
+    latest_entry_range(&page.contents, heading_index, version).map(|range| &page.contents[range])
+}
+
+/// Find the range of the latest entry in the Changed Pages section whose
+/// heading is at `heading_index`, if the entry is for `version`
+fn latest_entry_range(
+    contents: &[(String, u8)],
+    heading_index: usize,
+    version: &Version,
+) -> Option<Range<usize>> {
+    // Entries are confined to the section, so a later section is never
+    // mistaken for part of the latest entry
+    let section = section_body_at(contents, heading_index);
+
+    // Positions found in subslices are offset back to indices into `contents`
+    contents[section.clone()]
+        .iter()
+        .position(is_version_entry)
+        .map(|pos| pos + section.start)
+        .filter(|start| block_text(&contents[*start].0) == version_heading(version))
+        .map(|start| {
+            let end = contents[start + 1..section.end]
+                .iter()
+                .position(is_version_entry)
+                .map_or(section.end, |pos| pos + start + 1);
+            start..end
+        })
+}
+
+/// Get all pages recorded in the changelog of a version page, across entries
+pub fn recorded_pages(page: &LogseqPage, headings: &ChangelogHeadings) -> ChangedPages {
+    let mut recorded = ChangedPages::default();
+    let mut entry_starts = page
+        .contents
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| is_version_entry(line))
+        .map(|(i, _)| i)
+        .peekable();
+    while let Some(start) = entry_starts.next() {
+        let end = entry_starts.peek().copied().unwrap_or(page.contents.len());
+        let (changes, _) = parse_entry(&page.contents[start + 1..end], headings);
+        recorded.added.extend(changes.added);
+        recorded.modified.extend(changes.modified);
+        recorded.deleted.extend(changes.deleted);
+    }
+    recorded
+}
+
+/// Remove duplicate pages from the changelog entries of a version page
+///
+/// Within each entry, a page is kept only at its first occurrence in each
+/// subsection. A page recorded as added is dropped from Modified and Deleted,
+/// and a deleted page is dropped from Modified. Subsections left empty are
+/// removed. Returns the cleaned page and the number of entries removed.
+pub fn dedupe_changed_pages(
+    page: &LogseqPage,
+    headings: &ChangelogHeadings,
+) -> (LogseqPage, usize) {
+    let entry_starts = page
+        .contents
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| is_version_entry(line))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let first_entry = entry_starts.first().copied().unwrap_or(page.contents.len());
+    let mut contents = page.contents[..first_entry].to_vec();
+    let mut removed = 0;
+    for (i, start) in entry_starts.iter().enumerate() {
+        let end = entry_starts
+            .get(i + 1)
+            .copied()
+            .unwrap_or(page.contents.len());
+        let (body, entry_removed) = dedupe_entry(&page.contents[start + 1..end], headings);
+        contents.push(page.contents[*start].clone());
+        contents.extend(body);
+        removed += entry_removed;
+    }
+    (
+        LogseqPage::new(&page.title, page.properties.clone(), contents),
+        removed,
+    )
+}
+
+/// Remove duplicate pages from the body of a version entry
+fn dedupe_entry(body: &[(String, u8)], headings: &ChangelogHeadings) -> (Vec<(String, u8)>, usize) {
+    let (changes, _) = parse_entry(body, headings);
+    let added = changes.added.into_iter().collect::<HashSet<_>>();
+    let deleted = changes.deleted.into_iter().collect::<HashSet<_>>();
+
+    let mut seen = HashSet::new();
+    let mut kept = Vec::new();
+    let mut removed = 0;
+    let mut section = None;
+    let mut dropping_children = false;
+    for (line, indent) in body {
+        if *indent <= 2 {
+            section = change_section(line, *indent, headings);
+        }
+        if dropping_children && *indent > 3 {
+            continue;
+        }
+        dropping_children = false;
+
+        let page = listed_page(line);
+        if let (Some(section), Some(page), 3) = (section, page, *indent) {
+            let superseded = match section {
+                1 => added.contains(page) || deleted.contains(page),
+                2 => added.contains(page),
+                _ => false,
+            };
+            if !seen.insert((section, page)) || superseded {
+                removed += 1;
+                dropping_children = true;
+                continue;
+            }
+        }
+        kept.push((line.clone(), *indent));
+    }
+
+    // Drop subsection headings whose pages were all removed
+    let kept = kept
+        .iter()
+        .enumerate()
+        .filter(|(i, (line, indent))| {
+            change_section(line, *indent, headings).is_none()
+                || kept.get(i + 1).is_some_and(|(_, next)| *next > 2)
+        })
+        .map(|(_, line)| line.clone())
+        .collect();
+    (kept, removed)
+}
+
+/// Empty sections and entries removed from a version page by pruning
+#[derive(Debug, Default)]
+pub struct Pruned {
+    /// Number of Added, Modified and Deleted headings without pages
+    pub sections: usize,
+    /// Number of version entries left without any content
+    pub entries: usize,
+}
+
+impl Pruned {
+    /// Check if nothing was pruned
+    pub fn is_empty(&self) -> bool {
+        self.sections == 0 && self.entries == 0
+    }
+}
+
+impl fmt::Display for Pruned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} empty sections, {} empty entries",
+            self.sections, self.entries
+        )
+    }
+}
+
+/// Remove empty sections and entries from the changelog of a version page
+///
+/// An Added, Modified or Deleted heading without pages under it is removed,
+/// and then a version entry left with no blocks at all. Entries holding
+/// custom subsections are kept.
+pub fn prune_empty_sections(
+    page: &LogseqPage,
+    headings: &ChangelogHeadings,
+) -> (LogseqPage, Pruned) {
+    let lines = &page.contents;
+    let mut pruned = Pruned::default();
+    let mut contents = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !is_version_entry(&lines[i]) {
+            contents.push(lines[i].clone());
+            i += 1;
+            continue;
+        }
+        // The entry ends at the next entry or top-level block, and the blank
+        // lines before it stay in place
+        let end = lines[i + 1..]
+            .iter()
+            .position(|(line, indent)| *indent <= 1 && !line.is_empty())
+            .map_or(lines.len(), |pos| pos + i + 1);
+        let body_end = lines[i + 1..end]
+            .iter()
+            .rposition(|(line, _)| !line.is_empty())
+            .map_or(i + 1, |pos| pos + i + 2);
+        let body = &lines[i + 1..body_end];
+
+        let kept = body
+            .iter()
+            .enumerate()
+            .filter(|(j, (line, indent))| {
+                let has_pages = body[j + 1..]
+                    .iter()
+                    .find(|(line, _)| !line.is_empty())
+                    .is_some_and(|(_, next)| *next > 2);
+                let empty = change_section(line, *indent, headings).is_some() && !has_pages;
+                if empty {
+                    pruned.sections += 1;
+                }
+                !empty
+            })
+            .map(|(_, line)| line.clone())
+            .collect::<Vec<_>>();
+        if kept.iter().all(|(line, _)| line.is_empty()) {
+            pruned.entries += 1;
+        } else {
+            contents.push(lines[i].clone());
+            contents.extend(kept);
+        }
+        contents.extend_from_slice(&lines[body_end..end]);
+        i = end;
+    }
+    (
+        LogseqPage::new(&page.title, page.properties.clone(), contents),
+        pruned,
+    )
+}
+
+/// Check if a version page has a Changed Pages heading
+pub fn has_changed_pages_heading(page: &LogseqPage, headings: &ChangelogHeadings) -> bool {
+    page.contents
+        .iter()
+        .any(|line| is_changed_pages_heading(line, headings))
+}
+
+/// Find the index of the Changed Pages heading, appending it if missing
+fn changed_pages_heading(contents: &mut Vec<(String, u8)>, headings: &ChangelogHeadings) -> usize {
+    match contents
+        .iter()
+        .position(|line| is_changed_pages_heading(line, headings))
+    {
+        Some(index) => index,
+        None => {
+            contents.push((headings.changed_pages.clone(), 0));
+            contents.len() - 1
+        }
+    }
+}
+
+/// Check if a content line is the Changed Pages heading
+fn is_changed_pages_heading((line, indent): &(String, u8), headings: &ChangelogHeadings) -> bool {
+    block_text(line) == headings.changed_pages && *indent == 0
+}
+
+/// Get the heading line of a version entry
+fn version_heading(version: &Version) -> String {
+    format!("## [[{}]]", version_page_title(version))
+}
+
+/// Check if a content line starts a version entry
+fn is_version_entry((line, indent): &(String, u8)) -> bool {
+    line.starts_with("## [[") && *indent == 1
+}
+
+/// Get the index in [`ChangelogHeadings::change_sections`] of a subsection
+/// heading line
+fn change_section(line: &str, indent: u8, headings: &ChangelogHeadings) -> Option<usize> {
+    headings
+        .change_sections()
+        .iter()
+        .position(|heading| indent == 2 && block_text(line) == *heading)
+}
+
+/// Parse the body of a version entry
+///
+/// Returns the pages listed under the Added/Modified/Deleted subsections and
+/// the lines of all other subsections, which are kept verbatim.
+fn parse_entry(
+    body: &[(String, u8)],
+    headings: &ChangelogHeadings,
+) -> (ChangedPages, Vec<(String, u8)>) {
+    let mut changes = ChangedPages::default();
+    let mut custom_sections = Vec::new();
+    let mut section = None;
+
+    for (line, indent) in body {
+        if *indent <= 2 {
+            section = change_section(line, *indent, headings);
+        }
+        let pages = match section {
+            Some(0) => &mut changes.added,
+            Some(1) => &mut changes.modified,
+            Some(2) => &mut changes.deleted,
+            _ => {
+                custom_sections.push((line.clone(), *indent));
+                continue;
+            }
+        };
+        if *indent == 3 {
+            if let Some(page) = listed_page(line) {
+                pages.push(page.to_string());
+            }
+        }
+    }
+
+    (changes, custom_sections)
+}
+
+/// Replace the contents of the changelog section of a version page
+///
+/// The section spans from the Changed Pages heading to the next top-level
+/// block, and blank lines before that block are kept. The heading is
+/// appended if the page doesn't have one.
+pub fn replace_changed_pages(
+    page: &mut LogseqPage,
+    entries: Vec<(String, u8)>,
+    headings: &ChangelogHeadings,
+) {
+    let heading_index = changed_pages_heading(&mut page.contents, headings);
+    let section = section_body_at(&page.contents, heading_index);
+    page.contents.splice(section, entries);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a content line
+    fn line(text: &str, indent: u8) -> (String, u8) {
+        (text.to_string(), indent)
+    }
+
+    /// Build a version page with the given Changed Pages section body
+    fn version_page(entries: Vec<(String, u8)>) -> LogseqPage {
+        let mut contents = vec![
+            line("# Summary", 0),
+            line("", 0),
+            line("# Changed Pages", 0),
+        ];
+        contents.extend(entries);
+        LogseqPage::new("1.2.0", vec![("tags".into(), "Version".into())], contents)
+    }
+
+    /// Build changes adding the given pages
+    fn added(pages: &[&str]) -> ChangedPages {
+        ChangedPages {
+            added: pages.iter().map(|page| page.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn merge_keeps_custom_subsections_and_page_notes() {
+        let existing = version_page(vec![
+            line("## [[1.2.0]]", 1),
+            line("### Added", 2),
+            line("[[Foo]]\nid:: 6500a1b2-0000-4000-8000-000000000001", 3),
+            line("Reviewed by the pharmacy team", 4),
+            line("Dose table pending", 5),
+            line("### Notes", 2),
+            line("Released early for the audit", 3),
+        ]);
+        let merged = merge_changed_pages(
+            &existing,
+            &added(&["Bar"]),
+            &version("1.2.0"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        assert_eq!(
+            merged.contents[3..],
+            [
+                line("## [[1.2.0]]", 1),
+                line("### Added", 2),
+                line("[[Bar]]", 3),
+                line("[[Foo]]\nid:: 6500a1b2-0000-4000-8000-000000000001", 3),
+                line("Reviewed by the pharmacy team", 4),
+                line("Dose table pending", 5),
+                line("### Notes", 2),
+                line("Released early for the audit", 3),
+            ]
+        );
+
+        // A second sync changes nothing else
+        let resynced = merge_changed_pages(
+            &merged,
+            &added(&["Bar"]),
+            &version("1.2.0"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        assert_eq!(resynced, merged);
+    }
+
+    #[test]
+    fn merge_adds_a_new_entry_above_older_versions() {
+        let existing = version_page(vec![
+            line("## [[1.1.0]]", 1),
+            line("### Added", 2),
+            line("[[Old]]", 3),
+        ]);
+        let changes = ChangedPages {
+            added: vec!["Zinc".into(), "Aspirin".into()],
+            deleted: vec!["Gone".into()],
+            ..Default::default()
+        };
+        let merged = merge_changed_pages(
+            &existing,
+            &changes,
+            &version("1.2.0"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        assert_eq!(
+            merged.contents[3..],
+            [
+                line("## [[1.2.0]]", 1),
+                line("### Added", 2),
+                line("[[Aspirin]]", 3),
+                line("[[Zinc]]", 3),
+                line("### Deleted", 2),
+                line("[[Gone]]", 3),
+                line("## [[1.1.0]]", 1),
+                line("### Added", 2),
+                line("[[Old]]", 3),
+            ]
+        );
+        assert_eq!(merged.properties, existing.properties);
+    }
+
+    #[test]
+    fn merge_into_the_current_entry_deduplicates_pages() {
+        let existing = version_page(vec![
+            line("## [[1.2.0]]", 1),
+            line("### Modified", 2),
+            line("[[Aspirin]]", 3),
+        ]);
+        let changes = ChangedPages {
+            modified: vec!["Aspirin".into(), "Metformin".into()],
+            ..Default::default()
+        };
+        let merged = merge_changed_pages(
+            &existing,
+            &changes,
+            &version("1.2.0"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        assert_eq!(
+            merged.contents[3..],
+            [
+                line("## [[1.2.0]]", 1),
+                line("### Modified", 2),
+                line("[[Aspirin]]", 3),
+                line("[[Metformin]]", 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn recreate_discards_the_recorded_pages() {
+        let existing = version_page(vec![
+            line("## [[1.2.0]]", 1),
+            line("### Added", 2),
+            line("[[Stale]]", 3),
+        ]);
+        let recreated = recreate_changed_pages(
+            &existing,
+            &added(&["Fresh"]),
+            &version("1.2.0"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        assert_eq!(
+            recreated.contents[3..],
+            [
+                line("## [[1.2.0]]", 1),
+                line("### Added", 2),
+                line("[[Fresh]]", 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_appends_a_missing_changed_pages_heading() {
+        let existing = LogseqPage::new(
+            "1.2.0",
+            vec![("tags".into(), "Version".into())],
+            vec![line("# Summary", 0), line("First release", 1)],
+        );
+        let merged = merge_changed_pages(
+            &existing,
+            &added(&["Aspirin"]),
+            &version("1.2.0"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        assert_eq!(
+            merged.contents,
+            [
+                line("# Summary", 0),
+                line("First release", 1),
+                line("# Changed Pages", 0),
+                line("## [[1.2.0]]", 1),
+                line("### Added", 2),
+                line("[[Aspirin]]", 3),
+            ]
+        );
+        assert!(has_changed_pages_heading(
+            &merged,
+            &ChangelogHeadings::default()
+        ));
+    }
+
+    #[test]
+    fn merge_only_touches_the_latest_entry() {
+        let older = "    - ## [[1.1.0]]\n        - ### Added\n            - [[Old]]\n    - ## [[1.0.0]]\n        - ### Modified\n            - [[Older]]\n";
+        let after = "\n- # Related\n    - [[Roadmap]]\n";
+        let text = format!(
+            "tags:: Version\n\n- # Changed Pages\n    - ## [[1.2.0]]\n        - ### Added\n            - [[Aspirin]]\n{}{}",
+            older, after
+        );
+        let lines = text.lines().map(String::from).collect::<Vec<_>>();
+        let existing = LogseqPage::parse("1.2.0", &lines);
+
+        let merged = merge_changed_pages(
+            &existing,
+            &added(&["Metformin"]),
+            &version("1.2.0"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        let merged_text = merged.to_text(Default::default());
+        assert_eq!(
+            merged_text,
+            format!(
+                "tags:: Version\n\n- # Changed Pages\n    - ## [[1.2.0]]\n        - ### Added\n            - [[Aspirin]]\n            - [[Metformin]]\n{}{}",
+                older, after
+            )
+        );
+    }
+
+    #[test]
+    fn pre_releases_get_their_own_page() {
+        assert_eq!(version_page_title(&version("1.2.3-rc.1")), "1.2.3-rc.1");
+        assert_eq!(version_page_title(&version("1.2.3+build.7")), "1.2.3");
+        assert_eq!(
+            version_page_title(&version("1.2.3-rc.1+build.7")),
+            "1.2.3-rc.1"
+        );
+        assert_eq!(
+            overflow_page_title(&version("1.2.3-rc.1")),
+            format!("1.2.3-rc.1{}", OVERFLOW_SUFFIX)
+        );
+    }
+
+    #[test]
+    fn pre_release_entry_links_to_its_page() {
+        let existing = version_page(vec![
+            line("## [[1.2.3]]", 1),
+            line("### Added", 2),
+            line("[[Old]]", 3),
+        ]);
+        let merged = merge_changed_pages(
+            &existing,
+            &added(&["Aspirin"]),
+            &version("1.2.4-rc.1+build.7"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        assert_eq!(
+            merged.contents[3..],
+            [
+                line("## [[1.2.4-rc.1]]", 1),
+                line("### Added", 2),
+                line("[[Aspirin]]", 3),
+                line("## [[1.2.3]]", 1),
+                line("### Added", 2),
+                line("[[Old]]", 3),
+            ]
+        );
+
+        // The build metadata doesn't make another entry
+        let resynced = merge_changed_pages(
+            &merged,
+            &added(&["Aspirin"]),
+            &version("1.2.4-rc.1"),
+            &ChangelogHeadings::default(),
+            ChangelogOrder::Title,
+        );
+        assert_eq!(resynced, merged);
+    }
+
+    #[test]
+    fn custom_headings_are_read_and_written() {
+        let headings = ChangelogHeadings {
+            summary: "# Zusammenfassung".to_string(),
+            changed_pages: "# Geänderte Seiten".to_string(),
+            added: "### Neu".to_string(),
+            modified: "### Geändert".to_string(),
+            deleted: "### Entfernt".to_string(),
+        };
+        let mut existing = LogseqPage::new(
+            "1.2.0",
+            vec![],
+            vec![
+                line("# Zusammenfassung", 0),
+                line("Erste Fassung", 1),
+                line("", 0),
+                line("# Geänderte Seiten", 0),
+                line("## [[1.2.0]]", 1),
+                line("### Geändert", 2),
+                line("[[Aspirin]]", 3),
+            ],
+        );
+        assert_eq!(
+            summary(&existing, &headings),
+            Some(vec![line("Erste Fassung", 1)])
+        );
+        add_summary(&mut existing, "Dosierung ergänzt", &headings).unwrap();
+        assert!(has_changed_pages_heading(&existing, &headings));
+        assert!(!has_changed_pages_heading(
+            &existing,
+            &ChangelogHeadings::default()
+        ));
+
+        let changes = ChangedPages {
+            added: vec!["Metformin".into()],
+            modified: vec!["Aspirin".into(), "Zinc".into()],
+            deleted: vec!["Gone".into()],
+            ..Default::default()
+        };
+        let merged = merge_changed_pages(
+            &existing,
+            &changes,
+            &version("1.2.0"),
+            &headings,
+            ChangelogOrder::Title,
+        );
+        assert_eq!(
+            merged.contents[4..],
+            [
+                line("# Geänderte Seiten", 0),
+                line("## [[1.2.0]]", 1),
+                line("### Neu", 2),
+                line("[[Metformin]]", 3),
+                line("### Geändert", 2),
+                line("[[Aspirin]]", 3),
+                line("[[Zinc]]", 3),
+                line("### Entfernt", 2),
+                line("[[Gone]]", 3),
+            ]
+        );
+        let recorded = recorded_pages(&merged, &headings);
+        assert_eq!(recorded.modified, vec!["Aspirin", "Zinc"]);
+        assert_eq!(recorded.deleted, vec!["Gone"]);
+    }
+}
@@ -0,0 +1,360 @@
+/// SVLMD configuration
+///
+/// The configuration is stored as JSON in the `.svlmd` file at the project root.
+/// Defaults shared across vaults can be kept in a user-level file, see
+/// [`user_config_path`], which the vault's file is layered over.
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+/// Contents of the `.svlmd` configuration file
+///
+/// Unknown keys are rejected, so a typo'd key fails loading instead of being
+/// silently ignored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Display name of the contributor. May be left to the user-level
+    /// configuration, but must be set in one of the layers.
+    #[serde(default)]
+    pub contributor: String,
+    /// Email of the contributor, used for commit signatures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Whether page titles differing only in case refer to the same page.
+    /// Defaults to true on macOS and Windows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_insensitive_pages: Option<bool>,
+    /// Icon of the contributor's author page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// `strftime`-style format of dates written to pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+    /// Timezone of dates written to pages, as an IANA name (e.g.
+    /// `Asia/Bangkok`) or a fixed UTC offset (e.g. `+07:00`). Defaults to UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Where to read the current version from. Defaults to `version.txt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_source: Option<VersionSource>,
+    /// Directory holding the pages, relative to the project root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pages_dir: Option<String>,
+    /// Glob patterns of page files, relative to the pages directory, that are
+    /// left out of change detection (e.g. `_templates/*`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_pages: Vec<String>,
+    /// Maximum number of pages listed in a version entry. Entries listing
+    /// more move their pages to a `<version>/Changes` overflow page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_changed_pages: Option<usize>,
+    /// Number of times change detection retries opening the repository or
+    /// reading its status after a transient failure. Defaults to 2.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_retries: Option<u32>,
+    /// Tag sets that every page must carry at least one tag from.
+    /// Author and Version pages are exempt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_tags: Vec<Vec<String>>,
+    /// Whether to write properties in a deterministic order
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sort_properties: bool,
+    /// Properties written first, in this order, when sorting properties.
+    /// The remaining properties follow alphabetically.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub property_order: Vec<String>,
+    /// How page properties are written. Defaults to `key:: value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub property_style: Option<PropertyStyle>,
+    /// Headings of the sections of version pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog_headings: Option<ChangelogHeadings>,
+    /// How changed pages are linked in version entries. Defaults to the
+    /// plain `[[Title]]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog_links: Option<ChangelogLinks>,
+    /// How changed pages are sorted within version entries. Defaults to
+    /// sorting by title.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog_order: Option<ChangelogOrder>,
+}
+
+/// How changed pages are linked in version entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangelogLinks {
+    /// `[[Drug/Antibiotics/Amoxicillin]]`
+    #[default]
+    Title,
+    /// `[Amoxicillin]([[Drug/Antibiotics/Amoxicillin]])`, labelled with the
+    /// page's display name
+    Display,
+}
+
+/// How changed pages are sorted within the subsections of version entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangelogOrder {
+    /// Lexically by title
+    #[default]
+    Title,
+    /// By namespace, then by the last segment of the title, so pages of a
+    /// namespace such as `Drug/*` are listed together
+    Namespace,
+}
+
+/// Headings of the sections that `sync` writes to and reads from version
+/// pages
+///
+/// Each heading may be set on its own, the others keeping their defaults.
+/// Headings are matched as whole block texts, so they include their `#`
+/// markers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChangelogHeadings {
+    /// Top-level heading of the summary, `# Summary` by default
+    pub summary: String,
+    /// Top-level heading of the changelog, `# Changed Pages` by default
+    pub changed_pages: String,
+    /// Subsection listing added pages, `### Added` by default
+    pub added: String,
+    /// Subsection listing modified pages, `### Modified` by default
+    pub modified: String,
+    /// Subsection listing deleted pages, `### Deleted` by default
+    pub deleted: String,
+}
+
+impl Default for ChangelogHeadings {
+    fn default() -> Self {
+        Self {
+            summary: "# Summary".to_string(),
+            changed_pages: "# Changed Pages".to_string(),
+            added: "### Added".to_string(),
+            modified: "### Modified".to_string(),
+            deleted: "### Deleted".to_string(),
+        }
+    }
+}
+
+impl ChangelogHeadings {
+    /// Subsections of a version entry that are generated from changed pages,
+    /// in the order they are written
+    pub fn change_sections(&self) -> [&str; 3] {
+        [&self.added, &self.modified, &self.deleted]
+    }
+}
+
+/// Separator written between the key and value of page properties
+///
+/// Either style is read, as values are trimmed, but only the configured one
+/// is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PropertyStyle {
+    /// `key:: value`, as Logseq writes them
+    #[default]
+    Spaced,
+    /// `key::value`
+    Compact,
+}
+
+impl PropertyStyle {
+    /// Separator between key and value
+    pub fn separator(self) -> &'static str {
+        match self {
+            Self::Spaced => ":: ",
+            Self::Compact => "::",
+        }
+    }
+}
+
+/// Source of the current version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum VersionSource {
+    /// First line of a plain text file, relative to the project root
+    Text { path: String },
+    /// String at a dot-separated key path (e.g. `package.version`) of a JSON
+    /// file, relative to the project root
+    Json { path: String, key: String },
+    /// Latest `v*` git tag
+    GitTags,
+}
+
+impl fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text { path } => write!(f, "{}", path),
+            Self::Json { path, key } => write!(f, "key '{}' of {}", key, path),
+            Self::GitTags => write!(f, "git tags"),
+        }
+    }
+}
+
+impl VersionSource {
+    /// Path of the file holding the version, if the source is a file
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::Text { path } | Self::Json { path, .. } => Some(path),
+            Self::GitTags => None,
+        }
+    }
+}
+
+/// Path of the user-level configuration file
+///
+/// `$XDG_CONFIG_HOME/svlmd/config.json`, falling back to
+/// `~/.config/svlmd/config.json`. `None` if neither variable is set.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("svlmd").join("config.json"))
+}
+
+impl Config {
+    /// Load the configuration of a vault, layered over the user-level
+    /// configuration if it exists
+    ///
+    /// Top-level keys of the vault's file override those of the user-level
+    /// file. Nested values such as `version_source` are replaced whole.
+    pub fn load_layered(path: &Path) -> Result<Self> {
+        let user_path = user_config_path().filter(|user_path| user_path.is_file());
+        Self::load_layers(user_path.as_deref(), path)
+    }
+
+    /// Load the configuration of a vault layered over a user-level file,
+    /// if given, as `load_layered` does
+    fn load_layers(user_path: Option<&Path>, path: &Path) -> Result<Self> {
+        let mut merged = Map::new();
+        if let Some(user_path) = user_path {
+            merged.extend(load_object(user_path)?);
+        }
+        merged.extend(load_object(path)?);
+        let mut config: Self = serde_json::from_value(Value::Object(merged))
+            .context("Failed to merge configuration")?;
+        // Older configs may have stored the name untrimmed
+        config.contributor = config.contributor.trim().to_string();
+        if config.contributor.is_empty() {
+            bail!("No contributor set in {}", path.display());
+        }
+        Ok(config)
+    }
+
+    /// Load the configuration from a file
+    pub fn load(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Save the configuration to a file, overwriting it
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        serde_json::to_writer_pretty(&file, self)
+            .with_context(|| format!("Failed to write to {}", path.display()))
+    }
+}
+
+/// Read a configuration file as a JSON object of its keys
+///
+/// The file is first checked on its own, so unknown keys and values of the
+/// wrong type are reported with their position in that file.
+fn load_object(path: &Path) -> Result<Map<String, Value>> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    serde_json::from_str::<Config>(&text)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a configuration file into a temporary directory
+    fn config_file(text: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".svlmd");
+        fs::write(&path, text).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn unknown_key_is_rejected() {
+        let (_dir, path) = config_file(r#"{"contributor": "A", "date_fromat": "%d"}"#);
+
+        let error = format!("{:#}", load_object(&path).unwrap_err());
+        assert!(error.starts_with("Failed to parse"), "{}", error);
+        assert!(error.contains("unknown field `date_fromat`"), "{}", error);
+        assert!(error.contains("line 1 column"), "{}", error);
+    }
+
+    #[test]
+    fn wrong_typed_value_is_rejected() {
+        let (_dir, path) = config_file("{\n  \"contributor\": \"A\",\n  \"git_retries\": \"3\"\n}");
+
+        let error = format!("{:#}", load_object(&path).unwrap_err());
+        assert!(error.contains("invalid type: string \"3\""), "{}", error);
+        assert!(error.contains("line 3 column"), "{}", error);
+    }
+
+    #[test]
+    fn contributor_of_older_configs_is_trimmed() {
+        let (_dir, path) = config_file(r#"{"contributor": "  Sira  "}"#);
+        let config = Config::load_layers(None, &path).unwrap();
+        assert_eq!(config.contributor, "Sira");
+
+        let (_dir, path) = config_file(r#"{"contributor": "   "}"#);
+        let error = Config::load_layers(None, &path).unwrap_err();
+        assert!(error.to_string().starts_with("No contributor set in"));
+    }
+
+    #[test]
+    fn vault_config_overrides_user_config() {
+        let (_user_dir, user_path) = config_file(
+            r#"{"contributor": "User", "date_format": "%d/%m/%Y", "timezone": "+07:00",
+                "version_source": {"type": "json", "path": "package.json", "key": "version"}}"#,
+        );
+        let (_dir, path) = config_file(
+            r#"{"contributor": "Vault", "date_format": "%Y-%m-%d",
+                "version_source": {"type": "git-tags"}}"#,
+        );
+
+        let config = Config::load_layers(Some(&user_path), &path).unwrap();
+        assert_eq!(config.contributor, "Vault");
+        assert_eq!(config.date_format.as_deref(), Some("%Y-%m-%d"));
+        assert_eq!(config.timezone.as_deref(), Some("+07:00"));
+        assert!(matches!(
+            config.version_source,
+            Some(VersionSource::GitTags)
+        ));
+    }
+
+    #[test]
+    fn contributor_may_come_from_user_config() {
+        let (_user_dir, user_path) = config_file(r#"{"contributor": "User"}"#);
+        let (_dir, path) = config_file(r#"{"icon": "🩺"}"#);
+
+        let config = Config::load_layers(Some(&user_path), &path).unwrap();
+        assert_eq!(config.contributor, "User");
+        assert_eq!(config.icon.as_deref(), Some("🩺"));
+
+        let error = Config::load_layers(None, &path).unwrap_err();
+        assert!(error.to_string().starts_with("No contributor set in"));
+    }
+}
@@ -0,0 +1,246 @@
+/// Vault health checks
+///
+/// Each check inspects one precondition of the other commands and reports
+/// whether it holds. Errors mark problems that make commands fail, warnings
+/// mark problems that only degrade their output.
+use anyhow::Result;
+use colored::Colorize;
+use git2::Repository;
+use serde::Serialize;
+use std::fmt;
+
+use crate::file_manager::FileManager;
+use crate::validate;
+use crate::version;
+
+/// Outcome of a check
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// Result of a single check
+#[derive(Serialize)]
+pub struct Check {
+    /// Name of the check
+    pub check: &'static str,
+    /// Outcome of the check
+    pub status: Status,
+    /// Human-readable explanation of the outcome
+    pub detail: String,
+}
+
+impl Check {
+    fn new(check: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            check,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            Status::Ok => "ok".green(),
+            Status::Warning => "warning".yellow(),
+            Status::Error => "error".red(),
+        };
+        write!(f, "[{}] {}: {}", status, self.check, self.detail)
+    }
+}
+
+/// Run all checks against the vault
+pub fn run(file_manager: &FileManager) -> Vec<Check> {
+    vec![
+        check_git_repository(file_manager),
+        check_pages_dir(file_manager),
+        check_version_source(file_manager),
+        check_date_format(file_manager),
+        check_author_pages(file_manager),
+        check_version_tags(file_manager),
+        check_version_pages(file_manager),
+    ]
+}
+
+/// Check that the project root is a git repository
+fn check_git_repository(file_manager: &FileManager) -> Check {
+    match Repository::open(&file_manager.root) {
+        Ok(_) => Check::new("git-repository", Status::Ok, "found"),
+        Err(e) => Check::new("git-repository", Status::Error, e.message()),
+    }
+}
+
+/// Check that the pages directory exists
+fn check_pages_dir(file_manager: &FileManager) -> Check {
+    let pages_dir = file_manager.pages_dir();
+    if pages_dir.is_dir() {
+        Check::new("pages-dir", Status::Ok, pages_dir.display().to_string())
+    } else {
+        Check::new(
+            "pages-dir",
+            Status::Error,
+            format!("{} not found", pages_dir.display()),
+        )
+    }
+}
+
+/// Check that the version source holds a semantic version
+fn check_version_source(file_manager: &FileManager) -> Check {
+    match version::read(file_manager) {
+        Ok(version) => Check::new(
+            "version-source",
+            Status::Ok,
+            format!("{} from {}", version, file_manager.version_source()),
+        ),
+        Err(e) => Check::new("version-source", Status::Error, format!("{:#}", e)),
+    }
+}
+
+/// Check that dates can be formatted with the configured format and timezone
+fn check_date_format(file_manager: &FileManager) -> Check {
+    match file_manager.today() {
+        Ok(today) => Check::new("date-format", Status::Ok, format!("today is {}", today)),
+        Err(e) => Check::new("date-format", Status::Error, format!("{:#}", e)),
+    }
+}
+
+/// Check that every author of commits touching pages has an author page
+fn check_author_pages(file_manager: &FileManager) -> Check {
+    match missing_author_pages(file_manager) {
+        Ok(missing) if missing.is_empty() => {
+            Check::new("author-pages", Status::Ok, "all contributors have a page")
+        }
+        Ok(missing) => Check::new(
+            "author-pages",
+            Status::Warning,
+            format!(
+                "no page for contributors: {} (recreate with --fix)",
+                missing.join(", ")
+            ),
+        ),
+        Err(e) => Check::new("author-pages", Status::Warning, format!("{:#}", e)),
+    }
+}
+
+/// Get the contributors without an author page
+///
+/// Contributors are the authors of commits touching pages. The configured
+/// contributor isn't checked: every command recreates their page before
+/// running, so it can't be missing here.
+fn missing_author_pages(file_manager: &FileManager) -> Result<Vec<String>> {
+    // A vault that isn't a git repository yet has no contributors
+    if Repository::open(&file_manager.root).is_err() {
+        return Ok(vec![]);
+    }
+    Ok(file_manager
+        .page_authors()?
+        .into_iter()
+        .filter(|name| !file_manager.logseq_page_exists(name))
+        .collect())
+}
+
+/// Recreate the author pages of contributors without one
+///
+/// Returns the names of the contributors whose pages were written.
+pub fn fix_author_pages(file_manager: &FileManager) -> Result<Vec<String>> {
+    let missing = missing_author_pages(file_manager)?;
+    for name in &missing {
+        file_manager.write_logseq_page(&file_manager.author_page(name))?;
+    }
+    Ok(missing)
+}
+
+/// Check that the version tags can be read
+fn check_version_tags(file_manager: &FileManager) -> Check {
+    match file_manager.version_tags() {
+        Ok(tags) => Check::new(
+            "version-tags",
+            Status::Ok,
+            format!("{} version tags", tags.len()),
+        ),
+        Err(e) => Check::new("version-tags", Status::Warning, format!("{:#}", e)),
+    }
+}
+
+/// Check that every Version-tagged page is titled with a semantic version
+fn check_version_pages(file_manager: &FileManager) -> Check {
+    let mut invalid = Vec::new();
+    let titles = match file_manager.list_pages() {
+        Ok(titles) => titles,
+        Err(e) => return Check::new("version-pages", Status::Warning, format!("{:#}", e)),
+    };
+    for title in titles {
+        match file_manager.read_logseq_page(&title) {
+            Ok(page) if !validate::check_version_title(&page).is_empty() => invalid.push(title),
+            Ok(_) => {}
+            Err(e) => return Check::new("version-pages", Status::Warning, format!("{:#}", e)),
+        }
+    }
+    if invalid.is_empty() {
+        Check::new(
+            "version-pages",
+            Status::Ok,
+            "all titles are semantic versions",
+        )
+    } else {
+        Check::new(
+            "version-pages",
+            Status::Warning,
+            format!("not semantic versions: {}", invalid.join(", ")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::tests::{init_git, vault, write_page_file};
+    use git2::Signature;
+
+    /// Commit every page file as `author`
+    fn commit_pages_as(repo: &Repository, author: &str) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["pages"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now(author, "author@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "Edit pages",
+            &tree,
+            parent.as_ref().into_iter().collect::<Vec<_>>().as_slice(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn commit_authors_without_a_page_are_reported_and_fixed() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        let repo = init_git(&file_manager);
+        write_page_file(&file_manager, "Aspirin", "- text\n");
+        commit_pages_as(&repo, "Alice");
+        write_page_file(&file_manager, "Bob", "tags:: Author\n");
+        write_page_file(&file_manager, "Metformin", "- text\n");
+        commit_pages_as(&repo, "Bob");
+
+        assert_eq!(missing_author_pages(&file_manager).unwrap(), vec!["Alice"]);
+        assert_eq!(fix_author_pages(&file_manager).unwrap(), vec!["Alice"]);
+        assert!(file_manager.logseq_page_exists("Alice"));
+        assert!(missing_author_pages(&file_manager).unwrap().is_empty());
+    }
+
+    #[test]
+    fn vault_without_git_has_no_missing_author_pages() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        assert!(missing_author_pages(&file_manager).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+/// Export renderers for other Markdown dialects
+///
+/// Besides Logseq Markdown and JSON, pages can be exported as CommonMark with
+/// YAML frontmatter, or as Org-mode. Both renderers nest each content block
+/// by its indentation, as a list item or a headline, and keep links in their
+/// `[[Title]]` form.
+use crate::file_manager::{self, LogseqPage};
+
+/// Render a page as CommonMark with its title and properties as YAML
+/// frontmatter
+///
+/// Blocks become nested list items, with the lines after their first, such
+/// as block properties, as continuation lines. Blank lines between blocks are
+/// kept.
+pub fn to_markdown(page: &LogseqPage) -> String {
+    let mut text = String::from("---\n");
+    text += &format!("title: {}\n", yaml_string(&page.title));
+    for (key, value) in exported_properties(page) {
+        text += &format!("{}: {}\n", key, yaml_string(value));
+    }
+    text += "---\n";
+    if !page.contents.is_empty() {
+        text.push('\n');
+    }
+    for (content, indentation) in &page.contents {
+        if content.is_empty() {
+            text.push('\n');
+            continue;
+        }
+        let indent = "  ".repeat(*indentation as usize);
+        let mut lines = content.lines();
+        match lines.next().unwrap_or_default().trim() {
+            "" => text += &format!("{}-\n", indent),
+            first => text += &format!("{}- {}\n", indent, first),
+        }
+        lines.for_each(|line| text += &format!("{}  {}\n", indent, line));
+    }
+    text
+}
+
+/// Render a page as Org-mode
+///
+/// The title and properties become `#+TITLE:` and `#+PROPERTY:` lines. Blocks
+/// become headlines one level deeper than their indentation, without any
+/// leading Markdown heading marker. Block properties move to a
+/// `:PROPERTIES:` drawer under the headline, followed by the other lines of
+/// the block.
+pub fn to_org(page: &LogseqPage) -> String {
+    let mut text = format!("#+TITLE: {}\n", page.title);
+    for (key, value) in exported_properties(page) {
+        text += &format!("#+PROPERTY: {} {}\n", key, value);
+    }
+    if !page.contents.is_empty() {
+        text.push('\n');
+    }
+    for (content, indentation) in &page.contents {
+        if content.is_empty() {
+            text.push('\n');
+            continue;
+        }
+        let level = *indentation as usize + 1;
+        // Body lines are indented past the stars, so none can start a headline
+        let body_indent = " ".repeat(level + 1);
+        let headline = file_manager::block_text(content)
+            .trim_start_matches('#')
+            .trim();
+        text += &format!("{} {}\n", "*".repeat(level), headline);
+
+        let properties = file_manager::block_properties(content);
+        if !properties.is_empty() {
+            text += &format!("{}:PROPERTIES:\n", body_indent);
+            for (key, value) in &properties {
+                text += &format!("{}:{}: {}\n", body_indent, key, value);
+            }
+            text += &format!("{}:END:\n", body_indent);
+        }
+        content
+            .lines()
+            .skip(1)
+            .filter(|line| file_manager::parse_property(line).is_none())
+            .for_each(|line| text += &format!("{}{}\n", body_indent, line));
+    }
+    text
+}
+
+/// Get the page properties to export, leaving out `title::`, which is
+/// exported as the title
+fn exported_properties(page: &LogseqPage) -> impl Iterator<Item = (&str, &str)> {
+    page.properties
+        .iter()
+        .filter(|(key, _)| key != "title")
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+}
+
+/// Quote a string as a double-quoted YAML scalar
+///
+/// JSON strings are valid YAML, so the escaping of `serde_json` is reused.
+fn yaml_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a page with properties, nested blocks, a block property and a
+    /// blank line
+    fn page() -> LogseqPage {
+        LogseqPage::new(
+            "Drug/Aspirin",
+            vec![
+                ("title".to_string(), "Drug/Aspirin".to_string()),
+                ("tags".to_string(), "Drug, \"NSAID\"".to_string()),
+            ],
+            vec![
+                ("## Dosage".to_string(), 0),
+                (
+                    "See [[Metformin]]\nid:: 6500a1b2-0000-4000-8000-000000000001".to_string(),
+                    1,
+                ),
+                ("".to_string(), 0),
+                ("Side effects".to_string(), 0),
+            ],
+        )
+    }
+
+    #[test]
+    fn markdown_has_frontmatter_and_nested_items() {
+        let text = to_markdown(&page());
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            [
+                "---",
+                r#"title: "Drug/Aspirin""#,
+                r#"tags: "Drug, \"NSAID\"""#,
+                "---",
+                "",
+                "- ## Dosage",
+                "  - See [[Metformin]]",
+                "    id:: 6500a1b2-0000-4000-8000-000000000001",
+                "",
+                "- Side effects",
+            ]
+        );
+        assert!(text.ends_with('\n'));
+    }
+
+    #[test]
+    fn org_has_headlines_and_property_drawers() {
+        let text = to_org(&page());
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            [
+                "#+TITLE: Drug/Aspirin",
+                r#"#+PROPERTY: tags Drug, "NSAID""#,
+                "",
+                "* Dosage",
+                "** See [[Metformin]]",
+                "   :PROPERTIES:",
+                "   :id: 6500a1b2-0000-4000-8000-000000000001",
+                "   :END:",
+                "",
+                "* Side effects",
+            ]
+        );
+        assert!(text.ends_with('\n'));
+    }
+
+    #[test]
+    fn pages_without_contents_have_only_a_header() {
+        let page = LogseqPage::new("Zinc", vec![], vec![]);
+        assert_eq!(to_markdown(&page), "---\ntitle: \"Zinc\"\n---\n");
+        assert_eq!(to_org(&page), "#+TITLE: Zinc\n");
+    }
+}
@@ -4,25 +4,49 @@
 pub struct ConfigNotFoundError;
 
 use anyhow::{Context, Result};
-use git2::{Repository, StatusOptions};
+use chrono::{DateTime, FixedOffset, Utc};
+use git2::{Delta, Oid, Repository, Signature, StatusOptions};
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
 use std::{
-    fs::{File, OpenOptions},
-    io::{BufRead, BufReader, Write},
-    path::{Path, PathBuf},
+    collections::{BTreeSet, HashMap},
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
 };
 
+use crate::config::{
+    ChangelogHeadings, ChangelogLinks, ChangelogOrder, Config, PropertyStyle, VersionSource,
+};
+use crate::ignore::IgnoreRules;
+use crate::links::{self, RenamedLine};
+use crate::rename_map;
+use crate::version;
+use crate::writer::{Disk, Writer};
+
 /// Represents a Logseq page with its metadata and content
 ///
 /// A Logseq page consists of:
 /// - A title
 /// - Properties (key-value pairs in the page header)
-/// - Contents (lines of text with indentation levels)
+/// - Contents (blocks of text with indentation levels)
+///
+/// A block's own properties (e.g. `id:: <uuid>` or `collapsed:: true`) follow
+/// its text on separate lines of the same entry. Use `block_text` and
+/// `block_properties` to access them.
+///
+/// In JSON, properties and contents are arrays of `[key, value]` and
+/// `[text, indentation]` pairs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LogseqPage {
     /// The title of the page
     pub title: String,
     /// Properties in the page header as key-value pairs
     pub properties: Vec<(String, String)>,
-    /// Page contents with indentation levels
+    /// Page content blocks with indentation levels
     pub contents: Vec<(String, u8)>,
 }
 
@@ -46,88 +70,241 @@ impl LogseqPage {
     /// - Parsing indentation levels
     /// - Removing bullet points
     /// - Preserving properties
+    /// - Attaching unbulleted `key:: value` lines to the preceding block as
+    ///   block properties
+    /// - Keeping lines of only whitespace as blank lines, and bullets without
+    ///   text as empty blocks (see `EMPTY_BLOCK`)
+    /// - Clamping indentation deeper than `u8::MAX` levels with a warning
     pub fn from_plain(title: &str, properties: Vec<(String, String)>, contents: &str) -> Self {
-        fn count_indentation(line: &str) -> u8 {
-            let spaces = line.chars().take_while(|c| *c == ' ').count() as u8;
-            spaces / 4
-        }
-        let contents = contents
-            .lines()
-            .map(|line| {
-                (
-                    line.trim().replacen("- ", "", 1).to_string(),
-                    count_indentation(line),
-                )
+        let count_indentation = |line_number: usize, line: &str| -> u8 {
+            indentation_level(line).unwrap_or_else(|level| {
+                eprintln!(
+                    "Warning: {} line {}: indentation level {} exceeds {}, clamping",
+                    title,
+                    line_number,
+                    level,
+                    u8::MAX
+                );
+                u8::MAX
             })
-            .collect::<Vec<(String, u8)>>();
+        };
+        let mut blocks: Vec<(String, u8)> = Vec::new();
+        let mut in_block = false;
+        for (i, line) in contents.lines().enumerate() {
+            if in_block && is_block_property(line) {
+                if let Some((block, _)) = blocks.last_mut() {
+                    block.push('\n');
+                    block.push_str(line.trim());
+                }
+                continue;
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                in_block = false;
+                blocks.push((String::new(), 0));
+                continue;
+            }
+            if trimmed == "-" {
+                in_block = true;
+                blocks.push((EMPTY_BLOCK.to_string(), count_indentation(i + 1, line)));
+                continue;
+            }
+            in_block = line.trim_start().starts_with("- ");
+            blocks.push((
+                trimmed.replacen("- ", "", 1).to_string(),
+                count_indentation(i + 1, line),
+            ));
+        }
         Self {
             title: title.to_string(),
             properties,
-            contents,
+            contents: blocks,
         }
     }
 
-    /// Write the page to the filesystem
+    /// Format the page as written to its file
     ///
-    /// Formats and writes the page with:
-    /// - Properties in the header
+    /// Formats the page with:
+    /// - Properties in the header, in the given style
     /// - Properly indented content
-    /// - Bullet points for each line
-    pub fn write_page(&self, pages_dir: &Path) -> Result<()> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(self.title_to_path(pages_dir))?;
-
-        self.properties.iter().for_each(|(key, value)| {
-            writeln!(file, "{}:: {}", key, value).unwrap();
-        });
-        writeln!(file).unwrap();
-
-        self.contents.iter().for_each(|(content, indentation)| {
-            if content.is_empty() {
-                writeln!(file).unwrap();
-            } else {
-                writeln!(
-                    file,
-                    "{}- {}",
-                    "    ".repeat(*indentation as usize),
-                    content
-                )
-                .unwrap();
-            }
-        });
-
-        Ok(())
+    /// - Bullet points for each block, followed by its block properties
+    pub fn to_text(&self, style: PropertyStyle) -> String {
+        let mut text = String::new();
+        for (key, value) in &self.properties {
+            text += &format!("{}{}{}\n", key, style.separator(), value);
+        }
+        if !self.properties.is_empty() {
+            text.push('\n');
+        }
+        text + &format_contents(&self.contents)
     }
 
     /// Read a page from the filesystem
     ///
     /// Parses a Logseq page file into a structured format by:
     /// - Extracting properties from the header
+    /// - Dropping the blank line that separates properties from contents
     /// - Preserving content with indentation
     pub fn read_page(&self, pages_dir: &Path) -> Result<Self> {
         let file = File::open(self.title_to_path(pages_dir))?;
         let reader = BufReader::new(file);
         let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
+        Ok(Self::parse(&self.title, &lines))
+    }
 
-        let properties_end = lines
-            .iter()
-            .position(|line| !line.contains("::"))
-            .unwrap_or(0);
+    /// Read only the properties of a page from the filesystem
+    ///
+    /// Reading stops at the first blank line after the properties, so the
+    /// contents of the page are usually neither read nor parsed.
+    pub fn read_properties(&self, pages_dir: &Path) -> Result<Vec<(String, String)>> {
+        let file = File::open(self.title_to_path(pages_dir))?;
+        let mut lines = Vec::new();
+        let mut started = false;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let blank = line.trim().is_empty();
+            lines.push(line);
+            if blank && started {
+                break;
+            }
+            started |= !blank;
+        }
+        Ok(Self::parse(&self.title, &lines).properties)
+    }
 
-        let properties = lines[..properties_end]
+    /// Parse the lines of a page file, as read by `read_page`
+    ///
+    /// Page properties are the leading `key:: value` lines, which may follow
+    /// blank lines. Like Logseq, a first block holding nothing but properties
+    /// (`- key:: value` with further properties below it) is read as the page
+    /// properties too. Either way, the properties are written back as the
+    /// header, so such pages are normalized on the next write. A first block
+    /// with text or children is content.
+    pub fn parse(title: &str, lines: &[String]) -> Self {
+        let start = lines
             .iter()
-            .map(|line| {
-                let parts: Vec<&str> = line.split("::").collect();
-                (parts[0].to_string(), parts[1].trim().to_string())
-            })
+            .position(|line| !line.trim().is_empty())
+            .unwrap_or(lines.len());
+        let header_end = lines[start..]
+            .iter()
+            .position(|line| parse_property(line).is_none())
+            .map_or(lines.len(), |offset| start + offset);
+
+        let (property_lines, properties_end) = if header_end > start {
+            (
+                lines[start..header_end]
+                    .iter()
+                    .map(String::as_str)
+                    .collect(),
+                header_end,
+            )
+        } else if let Some(end) = properties_block_end(lines, start) {
+            let first = lines[start].strip_prefix("- ").unwrap_or_default();
+            let rest = lines[start + 1..end].iter().map(String::as_str);
+            (std::iter::once(first).chain(rest).collect(), end)
+        } else {
+            (Vec::new(), 0)
+        };
+        let properties = property_lines
+            .into_iter()
+            .filter_map(parse_property)
             .collect();
 
-        let contents = lines[properties_end..].join("\n");
+        // Skip the blank line separating properties from contents
+        let contents_start = match lines.get(properties_end) {
+            Some(line) if properties_end > 0 && line.is_empty() => properties_end + 1,
+            _ => properties_end,
+        };
+        let contents = lines[contents_start..].join("\n");
+
+        Self::from_plain(title, properties, &contents)
+    }
+
+    /// Check if two pages have the same properties and contents
+    ///
+    /// Property order is ignored, so pages differing only in formatting or in
+    /// the order of their properties are considered equivalent.
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        let mut properties = self.properties.iter().collect::<Vec<_>>();
+        let mut other_properties = other.properties.iter().collect::<Vec<_>>();
+        properties.sort();
+        other_properties.sort();
+        properties == other_properties && self.contents == other.contents
+    }
+
+    /// Get the value of a page property
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the value of a page property, appending it if missing
+    pub fn set_property(&mut self, key: &str, value: &str) {
+        match self.properties.iter_mut().find(|(k, _)| k == key) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.properties.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Remove a page property
+    pub fn remove_property(&mut self, key: &str) {
+        self.properties.retain(|(k, _)| k != key);
+    }
+
+    /// Get the tags of the page from its `tags::` property
+    pub fn tags(&self) -> Vec<String> {
+        self.property("tags")
+            .map(crate::links::parse_page_list)
+            .unwrap_or_default()
+    }
+
+    /// Check if the page carries a tag, ignoring case
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Insert a block right after the first block whose text is `heading`
+    ///
+    /// The indentation of `block` is relative to the heading, so `1` inserts
+    /// it as the heading's first child. Fails if the page has no such heading.
+    pub fn insert_under_heading(&mut self, heading: &str, block: (String, u8)) -> Result<()> {
+        let (index, heading_indent) = self
+            .contents
+            .iter()
+            .enumerate()
+            .find(|(_, (line, _))| block_text(line) == heading)
+            .map(|(index, (_, indent))| (index, *indent))
+            .with_context(|| format!("{} has no '{}' heading", self.title, heading))?;
+        let (text, indent) = block;
+        self.contents
+            .insert(index + 1, (text, heading_indent.saturating_add(indent)));
+        Ok(())
+    }
+
+    /// Get the block IDs declared by `id::` block properties
+    pub fn block_ids(&self) -> Vec<String> {
+        self.contents
+            .iter()
+            .flat_map(|(content, _)| block_properties(content))
+            .filter(|(key, value)| key == "id" && !value.is_empty())
+            .map(|(_, id)| id)
+            .collect()
+    }
 
-        Ok(Self::from_plain(&self.title, properties, &contents))
+    /// Get the 1-based file line of a content entry as laid out by `write_page`
+    pub fn line_number(&self, index: usize) -> usize {
+        let header = if self.properties.is_empty() {
+            0
+        } else {
+            self.properties.len() + 1
+        };
+        let preceding_lines = self.contents[..index]
+            .iter()
+            .map(|(content, _)| content.lines().count().max(1))
+            .sum::<usize>();
+        header + preceding_lines + 1
     }
 
     /// Convert a page title to its filesystem path
@@ -135,154 +312,2220 @@ impl LogseqPage {
     /// Handles special characters in titles by:
     /// - Replacing forward slashes with triple underscores
     /// - Adding the .md extension
-    fn title_to_path(&self, pages_dir: &Path) -> PathBuf {
+    pub fn title_to_path(&self, pages_dir: &Path) -> PathBuf {
         pages_dir.join(self.title.replace("/", "___") + ".md")
     }
 }
 
+/// Pages changed in git, grouped by kind of change
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChangedPages {
+    /// Titles of added pages
+    pub added: Vec<String>,
+    /// Titles of modified pages
+    pub modified: Vec<String>,
+    /// Titles of deleted pages
+    pub deleted: Vec<String>,
+    /// Titles of pages with unresolved merge conflicts, which changelogs
+    /// don't record
+    pub conflicted: Vec<String>,
+}
+
+impl ChangedPages {
+    /// Number of changed pages
+    pub fn len(&self) -> usize {
+        self.added.len() + self.modified.len() + self.deleted.len() + self.conflicted.len()
+    }
+
+    /// Check if no pages changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.modified.is_empty()
+            && self.deleted.is_empty()
+            && self.conflicted.is_empty()
+    }
+
+    /// Summarize the number of changed pages, e.g. `12 added, 3 modified, 1 deleted`
+    ///
+    /// Conflicted pages are counted at the end, if there are any.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} added, {} modified, {} deleted",
+            self.added.len(),
+            self.modified.len(),
+            self.deleted.len()
+        );
+        if !self.conflicted.is_empty() {
+            summary += &format!(", {} conflicted", self.conflicted.len());
+        }
+        summary
+    }
+}
+
+/// Kind of change of a page, as sorted into `ChangedPages`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    Added,
+    Modified,
+    Deleted,
+    Conflicted,
+}
+
+impl Change {
+    /// Classify a git status
+    ///
+    /// Conflicts take precedence over other flags. Renames and type changes
+    /// (e.g. a page replaced by a symlink) count as modifications. Returns
+    /// `None` for unchanged or ignored files.
+    fn from_status(status: git2::Status) -> Option<Self> {
+        if status.is_conflicted() {
+            Some(Self::Conflicted)
+        } else if status.is_wt_new() || status.is_index_new() {
+            Some(Self::Added)
+        } else if status.is_wt_modified()
+            || status.is_wt_renamed()
+            || status.is_wt_typechange()
+            || status.is_index_modified()
+            || status.is_index_renamed()
+            || status.is_index_typechange()
+        {
+            Some(Self::Modified)
+        } else if status.is_wt_deleted() || status.is_index_deleted() {
+            Some(Self::Deleted)
+        } else {
+            None
+        }
+    }
+}
+
+/// Add repository-relative paths to the index and write it
+///
+/// Returns the updated index.
+fn stage_paths(repo: &Repository, paths: &[String]) -> Result<git2::Index> {
+    let mut index = repo.index().context("Failed to read git index")?;
+    for path in paths {
+        index
+            .add_path(Path::new(path))
+            .with_context(|| format!("Failed to stage {}", path))?;
+    }
+    index.write().context("Failed to write git index")?;
+    Ok(index)
+}
+
+/// Get the git status entries considered by change detection
+fn page_statuses<'r>(
+    repo: &'r Repository,
+    options: &ChangeOptions,
+    retries: u32,
+) -> Result<git2::Statuses<'r>> {
+    let mut status_opts = StatusOptions::new();
+    status_opts
+        .include_untracked(options.include_untracked)
+        .include_ignored(false)
+        .include_unmodified(false)
+        .show(git2::StatusShow::Index);
+    retry_git(retries, || repo.statuses(Some(&mut status_opts))).context("Failed to get git status")
+}
+
+/// First delay before retrying a failed git operation, doubled after each
+/// further failure
+const GIT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Run a git operation, retrying it up to `retries` times after transient
+/// failures
+///
+/// Failures reading or locking files, as happen briefly on network drives,
+/// are transient. Others, such as a missing repository or a corrupt index,
+/// are returned at once.
+fn retry_git<T>(
+    retries: u32,
+    mut operation: impl FnMut() -> Result<T, git2::Error>,
+) -> Result<T, git2::Error> {
+    let mut delay = GIT_RETRY_DELAY;
+    for _ in 0..retries {
+        match operation() {
+            Err(e) if is_transient(&e) => {
+                eprintln!(
+                    "Warning: {}, retrying in {} ms",
+                    e.message(),
+                    delay.as_millis()
+                );
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    operation()
+}
+
+/// Check whether a git error may go away when the operation is retried
+fn is_transient(error: &git2::Error) -> bool {
+    match error.code() {
+        git2::ErrorCode::Locked => true,
+        git2::ErrorCode::NotFound => false,
+        _ => matches!(
+            error.class(),
+            git2::ErrorClass::Os | git2::ErrorClass::Filesystem
+        ),
+    }
+}
+
+/// Names of the git status flags, in the order they are shown
+const STATUS_FLAGS: [(git2::Status, &str); 12] = [
+    (git2::Status::INDEX_NEW, "INDEX_NEW"),
+    (git2::Status::INDEX_MODIFIED, "INDEX_MODIFIED"),
+    (git2::Status::INDEX_DELETED, "INDEX_DELETED"),
+    (git2::Status::INDEX_RENAMED, "INDEX_RENAMED"),
+    (git2::Status::INDEX_TYPECHANGE, "INDEX_TYPECHANGE"),
+    (git2::Status::WT_NEW, "WT_NEW"),
+    (git2::Status::WT_MODIFIED, "WT_MODIFIED"),
+    (git2::Status::WT_DELETED, "WT_DELETED"),
+    (git2::Status::WT_TYPECHANGE, "WT_TYPECHANGE"),
+    (git2::Status::WT_RENAMED, "WT_RENAMED"),
+    (git2::Status::IGNORED, "IGNORED"),
+    (git2::Status::CONFLICTED, "CONFLICTED"),
+];
+
+/// Decode git status flags into their names, e.g. `WT_NEW | INDEX_MODIFIED`
+///
+/// A status without flags is shown as `CURRENT`.
+pub fn describe_status(status: git2::Status) -> String {
+    let names = STATUS_FLAGS
+        .iter()
+        .filter(|(flag, _)| status.contains(*flag))
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        "CURRENT".to_string()
+    } else {
+        names.join(" | ")
+    }
+}
+
+/// Text of a bullet without text
+///
+/// An empty string is a blank line between blocks, so an empty bullet (`-`)
+/// is stored as a single space instead and written back as `-`.
+const EMPTY_BLOCK: &str = " ";
+
+/// Check if a content entry is a blank line or an empty block without block
+/// properties
+pub fn is_blank(content: &str) -> bool {
+    content.is_empty() || content == EMPTY_BLOCK
+}
+
+/// Get the indentation level of a line, four spaces per level
+///
+/// Fails with the level if it doesn't fit in a `u8`.
+fn indentation_level(line: &str) -> Result<u8, usize> {
+    let level = line.chars().take_while(|c| *c == ' ').count() / 4;
+    u8::try_from(level).map_err(|_| level)
+}
+
+/// Format content blocks as bulleted lines, as written to page files
+pub fn format_contents(contents: &[(String, u8)]) -> String {
+    let mut text = String::new();
+    for (content, indentation) in contents {
+        if content.is_empty() {
+            text.push('\n');
+            continue;
+        }
+        let indent = "    ".repeat(*indentation as usize);
+        let mut lines = content.lines();
+        match lines.next().unwrap_or_default() {
+            EMPTY_BLOCK => text += &format!("{}-\n", indent),
+            first => text += &format!("{}- {}\n", indent, first),
+        }
+        lines.for_each(|line| text += &format!("{}  {}\n", indent, line));
+    }
+    text
+}
+
+/// Truncate a page title to its first `depth` namespace levels
+///
+/// Namespaces are separated by `/`, so `Drug/Analgesic/Aspirin` at depth 1 is
+/// `Drug`. Titles with at most `depth` levels are returned unchanged.
+pub fn namespace_prefix(title: &str, depth: usize) -> &str {
+    match title.match_indices('/').nth(depth.saturating_sub(1)) {
+        Some((end, _)) if depth > 0 => &title[..end],
+        _ => title,
+    }
+}
+
+/// Get the text of a content block, without its block properties
+pub fn block_text(content: &str) -> &str {
+    content.lines().next().unwrap_or_default()
+}
+
+/// Get the block properties of a content block
+pub fn block_properties(content: &str) -> Vec<(String, String)> {
+    content.lines().skip(1).filter_map(parse_property).collect()
+}
+
+/// Parse a `key:: value` property line
+pub fn parse_property(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.trim().split_once("::")?;
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((key.to_string(), value.trim().to_string()))
+}
+
+/// Find the end of a first block holding nothing but properties
+///
+/// `start` is the line of the first block. Returns the line after the block,
+/// or `None` if the block has text other than properties or has children.
+fn properties_block_end(lines: &[String], start: usize) -> Option<usize> {
+    parse_property(lines.get(start)?.strip_prefix("- ")?)?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| !line.starts_with("  ") || !is_block_property(line))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    match lines.get(end) {
+        None => Some(end),
+        Some(line) if line.trim().is_empty() || line.starts_with('-') => Some(end),
+        Some(_) => None,
+    }
+}
+
+/// Check if a plain text line is a block property rather than a new block
+fn is_block_property(line: &str) -> bool {
+    !line.trim_start().starts_with("- ") && parse_property(line).is_some()
+}
+
+/// Options for detecting changed pages
+#[derive(Debug, Clone)]
+pub struct ChangeOptions {
+    /// Whether to include pages that aren't tracked yet
+    pub include_untracked: bool,
+    /// Whether to include modified pages whose properties and contents are
+    /// unchanged apart from formatting and property order
+    pub include_formatting: bool,
+}
+
+impl Default for ChangeOptions {
+    fn default() -> Self {
+        Self {
+            include_untracked: true,
+            include_formatting: false,
+        }
+    }
+}
+
+/// Icon of author pages when none is configured
+const DEFAULT_ICON: &str = "🙂";
+
 /// Manages file operations and Git integration for SVLMD
 #[derive(Debug, Clone)]
 pub struct FileManager {
     /// Root directory of the SVLMD project
     pub root: PathBuf,
-    /// Name of the current contributor
-    pub contributor_name: String,
+    /// Parsed configuration
+    config: Config,
+    /// Rules of the `.svlmdignore` file
+    ignore: IgnoreRules,
+    /// Destination of all changes to the vault
+    writer: Arc<dyn Writer>,
 }
 
 impl FileManager {
     /// Create a new FileManager instance
     ///
     /// Initializes by:
-    /// - Finding the project root
-    /// - Reading configuration
+    /// - Finding the project root, unless `root` overrides it
+    /// - Reading configuration, layered over the user-level configuration
     /// - Loading contributor information
-    pub fn new() -> Result<Self, ConfigNotFoundError> {
-        let root = detect_root().map_err(|_| ConfigNotFoundError)?;
+    /// - Reading the `.svlmdignore` file, if any
+    ///
+    /// Fails with `ConfigNotFoundError` if there is no `.svlmd`, and with the
+    /// parse error if it is invalid.
+    pub fn new(root: Option<&Path>) -> Result<Self> {
+        let root = match root {
+            Some(root) => root.to_path_buf(),
+            None => detect_root().map_err(|_| ConfigNotFoundError)?,
+        };
         let config_path = root.join(".svlmd");
 
         if config_path.exists() {
-            let file = File::open(config_path).map_err(|_| ConfigNotFoundError)?;
-            let reader = BufReader::new(file);
-            let config: serde_json::Value =
-                serde_json::from_reader(reader).map_err(|_| ConfigNotFoundError)?;
-            let contributor_name = config["contributor"].as_str().unwrap_or("").to_string();
+            let config = Config::load_layered(&config_path)?;
+            let ignore = IgnoreRules::load(&root)?;
             Ok(Self {
                 root,
-                contributor_name,
+                config,
+                ignore,
+                writer: Arc::new(Disk),
             })
         } else {
-            Err(ConfigNotFoundError)
+            Err(ConfigNotFoundError.into())
         }
     }
 
-    /// Check if a Logseq page exists
-    pub fn logseq_page_exists(&self, title: &str) -> bool {
-        let page_path = self
-            .root
-            .join("pages")
-            .join(title.replace("/", "___") + ".md");
-        page_path.exists()
+    /// Make all changes through `writer`, e.g. to preview them
+    pub fn with_writer(mut self, writer: Arc<dyn Writer>) -> Self {
+        self.writer = writer;
+        self
     }
 
-    /// Write a Logseq page to the filesystem
-    pub fn write_logseq_page(&self, page: &LogseqPage) -> Result<()> {
-        page.write_page(&self.root.join("pages"))
+    /// Destination of all changes to the vault
+    pub fn writer(&self) -> &dyn Writer {
+        self.writer.as_ref()
     }
 
-    /// Read a Logseq page from the filesystem
-    pub fn read_logseq_page(&self, title: &str) -> Result<LogseqPage> {
-        let page = LogseqPage::new(title, vec![], vec![]);
-        page.read_page(&self.root.join("pages"))
+    /// Name of the current contributor
+    pub fn contributor(&self) -> &str {
+        &self.config.contributor
+    }
+
+    /// Email of the current contributor, if configured
+    pub fn contributor_email(&self) -> Option<&str> {
+        self.config.email.as_deref()
     }
 
-    /// Get lists of changed pages from Git status
+    /// Icon of the contributor's author page
+    pub fn icon(&self) -> &str {
+        self.config.icon.as_deref().unwrap_or(DEFAULT_ICON)
+    }
+
+    /// Build the author page of a contributor
     ///
-    /// Returns an array of three vectors containing:
-    /// - [0]: New pages
-    /// - [1]: Modified pages
-    /// - [2]: Deleted pages
-    pub fn get_changed_pages(&self) -> Result<[Vec<String>; 3]> {
-        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+    /// The configured icon is used for the current contributor only, as
+    /// other contributors configure their own.
+    pub fn author_page(&self, name: &str) -> LogseqPage {
+        let icon = if name == self.contributor() {
+            self.icon()
+        } else {
+            DEFAULT_ICON
+        };
+        LogseqPage::new(
+            name,
+            vec![
+                ("icon".into(), icon.into()),
+                ("exclude-from-graph-view".into(), "true".into()),
+                ("tags".into(), "Author".into()),
+            ],
+            vec![],
+        )
+    }
 
-        let mut status_opts = StatusOptions::new();
-        status_opts
-            .include_untracked(true)
-            .include_ignored(false)
-            .include_unmodified(false)
-            .show(git2::StatusShow::Index);
+    /// `strftime`-style format of dates written to pages
+    pub fn date_format(&self) -> &str {
+        self.config.date_format.as_deref().unwrap_or("%Y-%m-%d")
+    }
 
-        let statuses = repo
-            .statuses(Some(&mut status_opts))
-            .context("Failed to get git status")?;
+    /// Source of the current version
+    ///
+    /// Unless configured, this is the first of [`version::VERSION_FILES`] at
+    /// the project root that holds a semantic version, falling back to
+    /// `version.txt` if none does.
+    pub fn version_source(&self) -> VersionSource {
+        if let Some(source) = &self.config.version_source {
+            return source.clone();
+        }
+        let path = version::VERSION_FILES
+            .iter()
+            .find(|path| version::read_text(self, path).is_ok())
+            .unwrap_or(&version::VERSION_FILES[0]);
+        VersionSource::Text {
+            path: path.to_string(),
+        }
+    }
 
-        // Sort pages into new, modified, and deleted
-        let mut new_pages = Vec::new();
-        let mut modified_pages = Vec::new();
-        let mut deleted_pages = Vec::new();
+    /// Whether the version source is set in the configuration, rather than
+    /// detected
+    pub fn version_source_configured(&self) -> bool {
+        self.config.version_source.is_some()
+    }
 
-        for entry in statuses.iter() {
-            let status = entry.status();
-            if let Some(path) = entry.path() {
-                if path.starts_with("pages/") && path.ends_with(".md") {
-                    let filename = path
-                        .strip_prefix("pages/")
-                        .unwrap()
-                        .strip_suffix(".md")
-                        .unwrap();
-                    let page_name = filename.replace("___", "/");
-                    if status.is_wt_new() || status.is_index_new() {
-                        new_pages.push(page_name);
-                    } else if status.is_wt_modified()
-                        || status.is_wt_renamed()
-                        || status.is_index_modified()
-                        || status.is_index_renamed()
-                    {
-                        modified_pages.push(page_name);
-                    } else if status.is_wt_deleted() || status.is_index_deleted() {
-                        deleted_pages.push(page_name);
-                    }
+    /// Tag sets that every page must carry at least one tag from
+    pub fn required_tags(&self) -> &[Vec<String>] {
+        &self.config.required_tags
+    }
+
+    /// Headings of the sections of version pages
+    pub fn changelog_headings(&self) -> ChangelogHeadings {
+        self.config.changelog_headings.clone().unwrap_or_default()
+    }
+
+    /// How changed pages are linked in version entries
+    pub fn changelog_links(&self) -> ChangelogLinks {
+        self.config.changelog_links.unwrap_or_default()
+    }
+
+    /// How changed pages are sorted within version entries
+    pub fn changelog_order(&self) -> ChangelogOrder {
+        self.config.changelog_order.unwrap_or_default()
+    }
+
+    /// Maximum number of pages listed in a version entry before they move to
+    /// an overflow page, if limited
+    pub fn max_changed_pages(&self) -> Option<usize> {
+        self.config.max_changed_pages
+    }
+
+    /// Number of times change detection retries a git operation that failed
+    /// transiently, 2 unless configured
+    pub fn git_retries(&self) -> u32 {
+        self.config.git_retries.unwrap_or(2)
+    }
+
+    /// Format a point in time as a date in the configured timezone and format
+    pub fn format_date(&self, time: DateTime<Utc>) -> Result<String> {
+        let format = self.date_format();
+        let Some(timezone) = self.config.timezone.as_deref() else {
+            return Ok(time.format(format).to_string());
+        };
+
+        if let Ok(offset) = timezone.parse::<FixedOffset>() {
+            return Ok(time.with_timezone(&offset).format(format).to_string());
+        }
+        let timezone = timezone
+            .parse::<chrono_tz::Tz>()
+            .map_err(|_| anyhow::anyhow!("Unknown timezone '{}' in .svlmd", timezone))?;
+        Ok(time.with_timezone(&timezone).format(format).to_string())
+    }
+
+    /// Get today's date in the configured timezone and format
+    pub fn today(&self) -> Result<String> {
+        self.format_date(Utc::now())
+    }
+
+    /// Resolve a path relative to the project root
+    ///
+    /// Fails for absolute paths, paths starting with `~`, and paths whose `..`
+    /// components lead out of the root, so paths taken from the config can't
+    /// reach outside the vault.
+    pub fn resolve(&self, relative: &str) -> Result<PathBuf> {
+        let path = Path::new(relative);
+        if relative.starts_with('~') {
+            anyhow::bail!("Path {} must be relative to the project root", relative);
+        }
+        let mut depth = 0usize;
+        for component in path.components() {
+            match component {
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    depth = depth.checked_sub(1).with_context(|| {
+                        format!("Path {} leads outside the project root", relative)
+                    })?
+                }
+                Component::RootDir | Component::Prefix(_) => {
+                    anyhow::bail!("Path {} must be relative to the project root", relative)
                 }
             }
         }
+        Ok(self.root.join(path))
+    }
 
-        Ok([new_pages, modified_pages, deleted_pages])
+    /// Read a file relative to the project root
+    pub fn read_to_string(&self, relative: &str) -> Result<String> {
+        fs::read_to_string(self.resolve(relative)?)
+            .with_context(|| format!("Failed to read {}", relative))
     }
-}
 
-/// Returns the path to the current executable
-pub fn get_executable_path() -> Result<PathBuf> {
-    std::env::current_exe().map_err(|e| anyhow::anyhow!("Failed to get executable path: {}", e))
-}
+    /// Write a file relative to the project root, overwriting it
+    pub fn write(&self, relative: &str, contents: impl AsRef<[u8]>) -> Result<()> {
+        self.writer
+            .write(&self.resolve(relative)?, contents.as_ref())
+            .with_context(|| format!("Failed to write {}", relative))
+    }
 
-/// Detects the root directory of the project
-///
-/// Searches for the .svlmd configuration file to determine
-/// the root directory of the SVLMD project
-pub fn detect_root() -> Result<PathBuf> {
-    let exe_path = get_executable_path()?;
-    fn fail() -> anyhow::Error {
-        anyhow::anyhow!("Failed to get executable directory")
+    /// Write a file relative to the project root atomically
+    ///
+    /// The contents are written to a temporary file next to it, which is then
+    /// moved over the file, so readers see either the old or the new file.
+    pub fn write_atomic(&self, relative: &str, contents: impl AsRef<[u8]>) -> Result<()> {
+        let path = self.resolve(relative)?;
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        self.writer
+            .write(&temporary, contents.as_ref())
+            .with_context(|| format!("Failed to write {}", relative))?;
+        self.writer.rename(&temporary, &path)
     }
-    // Get the directory containing the executable
-    let exe_dir = exe_path.parent().ok_or_else(fail)?;
 
-    // Check if we're in debug/release mode (in target directory)
-    if exe_dir.ends_with("debug") || exe_dir.ends_with("release") {
-        // Go up three levels: debug/release -> target -> project_root
-        return Ok(exe_dir
-            .parent() // target
-            .ok_or_else(fail)?
-            .parent() // svlmd
-            .ok_or_else(fail)?
-            .parent() // root
-            .ok_or_else(fail)?
-            .to_path_buf());
+    /// Directory holding the pages
+    pub fn pages_dir(&self) -> PathBuf {
+        self.root.join(self.pages_dir_name())
     }
 
-    // If we're running the installed binary, check current directory
-    if exe_dir.join("pages/").exists() {
-        return Ok(exe_dir.to_path_buf());
+    /// Path of the pages directory relative to the project root
+    fn pages_dir_name(&self) -> &str {
+        self.config
+            .pages_dir
+            .as_deref()
+            .unwrap_or("pages")
+            .trim_end_matches('/')
     }
 
-    anyhow::bail!("Failed to detect root directory. Please run svlmd from project root or installation directory.")
+    /// Check if a Logseq page exists
+    ///
+    /// Follows symlinks, so a symlinked page file counts as existing
+    /// as long as its target exists. When page titles are case-insensitive,
+    /// a page whose title differs only in case also counts as existing.
+    pub fn logseq_page_exists(&self, title: &str) -> bool {
+        self.find_page_title(title).is_some()
+    }
+
+    /// Find the title of an existing page as it is stored on disk
+    ///
+    /// Tries the exact title first. When page titles are case-insensitive,
+    /// falls back to comparing the lowercased titles of all pages.
+    pub fn find_page_title(&self, title: &str) -> Option<String> {
+        let page_path = self.pages_dir().join(title.replace("/", "___") + ".md");
+        if page_path.exists() {
+            return Some(title.to_string());
+        }
+
+        if !self.case_insensitive_pages() {
+            return None;
+        }
+        let title = title.to_lowercase();
+        self.list_pages()
+            .ok()?
+            .into_iter()
+            .find(|page| page.to_lowercase() == title)
+    }
+
+    /// Resolve a page name to the title of an existing page
+    ///
+    /// Accepts a title, a page filename such as `Drug___Aspirin.md` (also
+    /// with a leading directory), or an alias declared in an `alias::`
+    /// property, tried in that order.
+    pub fn resolve_page(&self, name: &str) -> Result<String> {
+        let name = name.trim();
+        if let Some(title) = self.find_page_title(name) {
+            return Ok(title);
+        }
+
+        let filename = Path::new(name)
+            .file_name()
+            .and_then(|filename| filename.to_str())
+            .unwrap_or(name);
+        if let Some(stem) = filename.strip_suffix(".md") {
+            if let Some(title) = self.find_page_title(&stem.replace("___", "/")) {
+                return Ok(title);
+            }
+        }
+
+        let pages = self
+            .list_pages()?
+            .iter()
+            .map(|title| self.read_logseq_page(title))
+            .collect::<Result<Vec<_>>>()?;
+        match links::AliasMap::new(&pages).resolve(name) {
+            Some(title) => Ok(title.to_string()),
+            None => anyhow::bail!("Page '{}' not found", name),
+        }
+    }
+
+    /// Whether page titles differing only in case refer to the same page
+    pub fn case_insensitive_pages(&self) -> bool {
+        self.config
+            .case_insensitive_pages
+            .unwrap_or(cfg!(any(target_os = "macos", target_os = "windows")))
+    }
+
+    /// Write a Logseq page to the filesystem
+    ///
+    /// When `sort_properties` is enabled, properties are written in the
+    /// configured order. Otherwise their order is preserved.
+    pub fn write_logseq_page(&self, page: &LogseqPage) -> Result<()> {
+        let page = self.prepare_write(page);
+        self.writer.write(
+            &self.page_path(&page.title)?,
+            page.to_text(self.property_style()).as_bytes(),
+        )
+    }
+
+    /// Get the path of the file of a page to write
+    ///
+    /// Fails if the file wouldn't be directly inside the pages directory.
+    /// `/` is escaped in filenames, so namespaced titles such as
+    /// `Drug/Aspirin` are stored flat, but titles from external sources could
+    /// still hold other separators, such as `\` or a drive prefix on Windows.
+    /// Titles reading as a path, with a `.`, `..` or empty segment between the
+    /// `/`, are refused as well. The check is made on the path as joined
+    /// rather than canonicalized, so symlinked page files keep working.
+    pub fn page_path(&self, title: &str) -> Result<PathBuf> {
+        let pages_dir = self.pages_dir();
+        let path = LogseqPage::new(title, vec![], vec![]).title_to_path(&pages_dir);
+        let path_like = title.contains('\\')
+            || title
+                .split('/')
+                .any(|segment| matches!(segment, "" | "." | ".."));
+        let inside = !path_like
+            && path.strip_prefix(&pages_dir).is_ok_and(|relative| {
+                let mut components = relative.components();
+                matches!(
+                    (components.next(), components.next()),
+                    (Some(Component::Normal(_)), None)
+                )
+            });
+        if !inside {
+            anyhow::bail!(
+                "Refusing to write page '{}' outside {}",
+                title,
+                pages_dir.display()
+            );
+        }
+        Ok(path)
+    }
+
+    /// Format a Logseq page exactly as `write_logseq_page` would write it
+    pub fn format_logseq_page(&self, page: &LogseqPage) -> String {
+        self.prepare_write(page).to_text(self.property_style())
+    }
+
+    /// Separator style of written page properties
+    pub fn property_style(&self) -> PropertyStyle {
+        self.config.property_style.unwrap_or_default()
+    }
+
+    /// Apply the configured property order to a page about to be written
+    pub fn prepare_write(&self, page: &LogseqPage) -> LogseqPage {
+        let mut page = page.clone();
+        if self.config.sort_properties {
+            sort_properties(&mut page.properties, &self.config.property_order);
+        }
+        page
+    }
+
+    /// Read a Logseq page from the filesystem
+    pub fn read_logseq_page(&self, title: &str) -> Result<LogseqPage> {
+        let page = LogseqPage::new(title, vec![], vec![]);
+        page.read_page(&self.pages_dir())
+    }
+
+    /// Read a Logseq page with only its properties, leaving its contents empty
+    pub fn read_page_properties(&self, title: &str) -> Result<LogseqPage> {
+        let page = LogseqPage::new(title, vec![], vec![]);
+        let properties = page.read_properties(&self.pages_dir())?;
+        Ok(LogseqPage::new(title, properties, vec![]))
+    }
+
+    /// List the titles of all Logseq pages, sorted
+    ///
+    /// Symlinks are followed, both for the pages directory itself and for
+    /// individual page files. Broken symlinks are skipped.
+    pub fn list_pages(&self) -> Result<Vec<String>> {
+        let pages_dir = self.pages_dir();
+        let mut titles = Vec::new();
+        for entry in fs::read_dir(&pages_dir)
+            .with_context(|| format!("Failed to read {}", pages_dir.display()))?
+        {
+            let path = entry?.path();
+            // `fs::metadata` follows symlinks, unlike `DirEntry::file_type`
+            let is_file = fs::metadata(&path).is_ok_and(|metadata| metadata.is_file());
+            if is_file && path.extension().is_some_and(|ext| ext == "md") {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    titles.push(stem.replace("___", "/"));
+                }
+            }
+        }
+        titles.sort();
+        Ok(titles)
+    }
+
+    /// List the page files in subdirectories of the pages directory, sorted
+    ///
+    /// Titles map to flat filenames, with `/` written as `___`, so these files
+    /// are never read as pages. Paths are relative to the pages directory,
+    /// with `/` separators. Files matching an `ignore_pages` pattern or
+    /// excluded by `.svlmdignore` are skipped.
+    pub fn nested_page_files(&self) -> Result<Vec<String>> {
+        let ignored = self.ignore_patterns()?;
+        let pages_dir = self.pages_dir();
+        let mut pending = Vec::new();
+        for entry in fs::read_dir(&pages_dir)
+            .with_context(|| format!("Failed to read {}", pages_dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            }
+        }
+
+        let mut files = Vec::new();
+        while let Some(dir) = pending.pop() {
+            for entry in
+                fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?
+            {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                if path.extension().is_none_or(|ext| ext != "md") {
+                    continue;
+                }
+                let Some(relative) = path
+                    .strip_prefix(&pages_dir)
+                    .ok()
+                    .and_then(|relative| relative.to_str())
+                else {
+                    continue;
+                };
+                let relative = relative.replace(std::path::MAIN_SEPARATOR, "/");
+                let repo_path = format!("{}/{}", self.pages_dir_name(), relative);
+                if !ignored.iter().any(|pattern| pattern.matches(&relative))
+                    && !self.ignore.is_ignored(&repo_path)
+                {
+                    files.push(relative);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Build the git signature for commits made by svlmd
+    ///
+    /// Uses the configured contributor name and email, falling back to the
+    /// repository's default signature if no email is configured.
+    pub fn signature(&self, repo: &Repository) -> Result<Signature<'static>> {
+        match self.contributor_email() {
+            Some(email) => Signature::now(self.contributor(), email),
+            None => repo.signature(),
+        }
+        .context("Failed to create git signature")
+    }
+
+    /// Create a git repository at the project root
+    ///
+    /// Returns false, without changes, if the root is already a repository.
+    pub fn init_repository(&self) -> Result<bool> {
+        if Repository::open(&self.root).is_ok() {
+            return Ok(false);
+        }
+        Repository::init(&self.root).with_context(|| {
+            format!("Failed to create git repository at {}", self.root.display())
+        })?;
+        Ok(true)
+    }
+
+    /// Plan renaming a page, without writing anything
+    ///
+    /// Fails if `from` doesn't exist or another page is titled `to`. With
+    /// `rewrite_links`, the links and tags referring to the page are renamed
+    /// in every page, including the renamed page itself.
+    pub fn plan_rename(&self, from: &str, to: &str, rewrite_links: bool) -> Result<PageRename> {
+        let Some(from) = self.find_page_title(from) else {
+            anyhow::bail!("Page '{}' not found", from);
+        };
+        if self
+            .find_page_title(to)
+            .is_some_and(|existing| existing != from)
+        {
+            anyhow::bail!("Page '{}' already exists", to);
+        }
+        self.page_path(to)?;
+
+        let mut pages = Vec::new();
+        if rewrite_links {
+            for title in self.list_pages()? {
+                let page = self.read_logseq_page(&title)?;
+                let (mut renamed, changed) =
+                    links::rename_links_in_page(&page, &from, to, self.property_style());
+                if title == from {
+                    renamed.title = to.to_string();
+                } else if changed.is_empty() {
+                    continue;
+                }
+                pages.push((renamed, changed));
+            }
+        }
+        Ok(PageRename {
+            from,
+            to: to.to_string(),
+            pages,
+        })
+    }
+
+    /// Rename a page, moving its file
+    ///
+    /// With `rewrite_links`, also rewrites the links and tags referring to
+    /// the page, as planned by `plan_rename`. Returns the number of
+    /// references updated.
+    pub fn rename_logseq_page(&self, from: &str, to: &str, rewrite_links: bool) -> Result<usize> {
+        let rename = self.plan_rename(from, to, rewrite_links)?;
+        let from_path =
+            LogseqPage::new(&rename.from, vec![], vec![]).title_to_path(&self.pages_dir());
+        self.writer
+            .rename(&from_path, &self.page_path(&rename.to)?)?;
+        for (page, _) in &rename.pages {
+            self.write_logseq_page(page)?;
+        }
+        Ok(rename.references())
+    }
+
+    /// Plan renaming several pages at once, without writing anything
+    ///
+    /// The whole map is validated first: every page to rename must exist and
+    /// be listed once, and no new title may be listed twice or belong to
+    /// another existing page. All problems are reported together, one per
+    /// line. The links and tags referring to the pages are then renamed in
+    /// one pass over the vault.
+    pub fn plan_batch_rename(&self, entries: &[rename_map::Entry]) -> Result<BatchRename> {
+        let mut problems = Vec::new();
+        let mut renames = Vec::new();
+        let mut sources: HashMap<String, &str> = HashMap::new();
+        let mut destinations: HashMap<String, &str> = HashMap::new();
+        for entry in entries {
+            let mut problem = |message: String| {
+                problems.push(format!(
+                    "{}: '{}' -> '{}': {}",
+                    entry.location, entry.from, entry.to, message
+                ))
+            };
+            if entry.from.is_empty() || entry.to.is_empty() {
+                problem("empty title".to_string());
+                continue;
+            }
+            let Some(from) = self.find_page_title(&entry.from) else {
+                problem(format!("page '{}' not found", entry.from));
+                continue;
+            };
+            if from == entry.to {
+                problem("the page is renamed to itself".to_string());
+                continue;
+            }
+            if let Some(location) = sources.insert(from.clone(), &entry.location) {
+                problem(format!("page '{}' is also renamed on {}", from, location));
+                continue;
+            }
+            if let Some(location) = destinations.insert(entry.to.to_lowercase(), &entry.location) {
+                problem(format!(
+                    "'{}' is also the new title on {}",
+                    entry.to, location
+                ));
+                continue;
+            }
+            if self
+                .find_page_title(&entry.to)
+                .is_some_and(|existing| existing != from)
+            {
+                problem(format!("page '{}' already exists", entry.to));
+                continue;
+            }
+            if let Err(e) = self.page_path(&entry.to) {
+                problem(format!("{:#}", e));
+                continue;
+            }
+            renames.push((from, entry.to.clone()));
+        }
+        if !problems.is_empty() {
+            anyhow::bail!(
+                "Invalid rename map, nothing was renamed:\n{}",
+                problems.join("\n")
+            );
+        }
+
+        let mut references = vec![0; renames.len()];
+        let mut pages = Vec::new();
+        for title in self.list_pages()? {
+            let mut page = self.read_logseq_page(&title)?;
+            let mut changed = 0;
+            for (i, (from, to)) in renames.iter().enumerate() {
+                let (renamed, lines) =
+                    links::rename_links_in_page(&page, from, to, self.property_style());
+                references[i] += lines.iter().map(|line| line.references).sum::<usize>();
+                changed += lines.len();
+                page = renamed;
+            }
+            let new_title = renames.iter().find(|(from, _)| *from == title);
+            if let Some((_, to)) = new_title {
+                page.title = to.clone();
+            } else if changed == 0 {
+                continue;
+            }
+            pages.push((page, changed));
+        }
+        Ok(BatchRename {
+            renames,
+            references,
+            pages,
+        })
+    }
+
+    /// Rename several pages as planned by `plan_batch_rename`
+    ///
+    /// Moves all page files before rewriting the pages referring to them.
+    pub fn batch_rename(&self, rename: &BatchRename) -> Result<()> {
+        let pages_dir = self.pages_dir();
+        for (from, to) in &rename.renames {
+            let from_path = LogseqPage::new(from, vec![], vec![]).title_to_path(&pages_dir);
+            self.writer.rename(&from_path, &self.page_path(to)?)?;
+        }
+        for (page, _) in &rename.pages {
+            self.write_logseq_page(page)?;
+        }
+        Ok(())
+    }
+
+    /// Get the repository-relative path of a page file
+    pub fn page_repo_path(&self, title: &str) -> String {
+        format!("{}/{}.md", self.pages_dir_name(), title.replace("/", "___"))
+    }
+
+    /// Check whether `.svlmdignore` excludes a page
+    pub fn is_ignored_page(&self, title: &str) -> bool {
+        self.ignore.is_ignored(&self.page_repo_path(title))
+    }
+
+    /// Stage the given repository-relative paths
+    pub fn stage(&self, paths: &[String]) -> Result<()> {
+        let description = format!("stage {}", paths.join(", "));
+        self.writer.run(&description, &mut || {
+            let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+            stage_paths(&repo, paths)?;
+            Ok(())
+        })
+    }
+
+    /// Commit the staged changes together with the given files
+    ///
+    /// Stages the given repository-relative paths and commits the index
+    /// using the contributor's signature.
+    pub fn commit(&self, paths: &[String], message: &str) -> Result<()> {
+        let description = format!("commit {}: {}", paths.join(", "), message);
+        self.writer.run(&description, &mut || {
+            let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+            let mut index = stage_paths(&repo, paths)?;
+
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let signature = self.signature(&repo)?;
+            let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+            let parents = parent.iter().collect::<Vec<_>>();
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .context("Failed to commit")?;
+            Ok(())
+        })
+    }
+
+    /// Get uncommitted changes outside the pages directory
+    ///
+    /// Returns the repository-relative paths of staged, unstaged, and
+    /// untracked files that aren't pages, except those in `allowed`.
+    pub fn dirty_paths_outside_pages(&self, allowed: &[&str]) -> Result<Vec<String>> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let mut status_opts = StatusOptions::new();
+        status_opts
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false)
+            .show(git2::StatusShow::IndexAndWorkdir);
+        let statuses = repo
+            .statuses(Some(&mut status_opts))
+            .context("Failed to get git status")?;
+
+        let pages_prefix = format!("{}/", self.pages_dir_name());
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| entry.path().map(String::from))
+            .filter(|path| !path.starts_with(&pages_prefix) && !allowed.contains(&path.as_str()))
+            .collect())
+    }
+
+    /// Get the titles of pages with unstaged edits
+    ///
+    /// Unlike `get_changed_pages`, which looks at the index, this compares the
+    /// working tree with the index, so it finds page edits that a commit would
+    /// leave out. Untracked pages aren't included.
+    pub fn unstaged_pages(&self) -> Result<Vec<String>> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let mut status_opts = StatusOptions::new();
+        status_opts
+            .include_untracked(false)
+            .include_ignored(false)
+            .show(git2::StatusShow::Workdir);
+        let statuses = repo
+            .statuses(Some(&mut status_opts))
+            .context("Failed to get git status")?;
+
+        let mut titles = statuses
+            .iter()
+            .filter(|entry| {
+                let status = entry.status();
+                status.is_wt_modified()
+                    || status.is_wt_deleted()
+                    || status.is_wt_renamed()
+                    || status.is_wt_typechange()
+            })
+            .filter_map(|entry| page_title_from_path(entry.path()?, self.pages_dir_name()))
+            .collect::<Vec<_>>();
+        titles.sort();
+        Ok(titles)
+    }
+
+    /// Create an annotated `v<version>` tag on HEAD
+    pub fn tag_version(&self, version: &semver::Version) -> Result<()> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let name = format!("v{}", version);
+        if repo.refname_to_id(&format!("refs/tags/{}", name)).is_ok() {
+            anyhow::bail!("Tag {} already exists", name);
+        }
+        self.writer.run(&format!("tag HEAD as {}", name), &mut || {
+            let head = repo
+                .head()
+                .and_then(|head| head.peel(git2::ObjectType::Commit))
+                .context("Failed to find HEAD commit")?;
+            let signature = self.signature(&repo)?;
+            repo.tag(
+                &name,
+                &head,
+                &signature,
+                &format!("Release {}", version),
+                false,
+            )
+            .with_context(|| format!("Failed to create tag {}", name))?;
+            Ok(())
+        })
+    }
+
+    /// Get the changed pages from Git status
+    ///
+    /// Only staged changes are considered. Without `include_untracked`, pages
+    /// that aren't tracked in the last commit yet are left out, so only
+    /// modified and deleted pages are reported. Pages with merge conflicts are
+    /// reported separately, whatever their other changes.
+    ///
+    /// Git stores a symlink as the path it points to, not as the files behind
+    /// it, so edits made through a symlinked pages directory or page file are
+    /// never reported. Such pages have to be tracked in the repository holding
+    /// their targets.
+    pub fn get_changed_pages(&self, options: &ChangeOptions) -> Result<ChangedPages> {
+        let retries = self.git_retries();
+        let repo = retry_git(retries, || Repository::open(&self.root))
+            .context("Failed to open git repository")?;
+        let statuses = page_statuses(&repo, options, retries)?;
+        let ignored = self.ignore_patterns()?;
+
+        let mut changed_pages = ChangedPages::default();
+        for entry in statuses.iter() {
+            let Some(path) = entry.path() else {
+                continue;
+            };
+            let Some(page_name) = self.changelog_page_title(path, &ignored) else {
+                continue;
+            };
+            match Change::from_status(entry.status()) {
+                Some(Change::Added) if options.include_untracked => {
+                    changed_pages.added.push(page_name)
+                }
+                Some(Change::Modified)
+                    if options.include_formatting
+                        || !is_formatting_change(&repo, path, &page_name)? =>
+                {
+                    changed_pages.modified.push(page_name)
+                }
+                Some(Change::Deleted) => changed_pages.deleted.push(page_name),
+                Some(Change::Conflicted) => changed_pages.conflicted.push(page_name),
+                _ => {}
+            }
+        }
+        Ok(changed_pages)
+    }
+
+    /// Get the raw git status of every page file that change detection sees
+    ///
+    /// Paths are relative to the repository root. Pages excluded by
+    /// `ignore_pages` or `.svlmdignore` are included, as this is meant for
+    /// diagnosing how `get_changed_pages` classifies pages.
+    pub fn raw_page_statuses(
+        &self,
+        options: &ChangeOptions,
+    ) -> Result<Vec<(String, git2::Status)>> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let statuses = page_statuses(&repo, options, self.git_retries())?;
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| Some((entry.path()?.to_string(), entry.status())))
+            .filter(|(path, _)| page_title_from_path(path, self.pages_dir_name()).is_some())
+            .collect())
+    }
+
+    /// Compile the configured `ignore_pages` patterns
+    fn ignore_patterns(&self) -> Result<Vec<Pattern>> {
+        self.config
+            .ignore_pages
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern)
+                    .with_context(|| format!("Invalid ignore_pages pattern '{}'", pattern))
+            })
+            .collect()
+    }
+
+    /// Convert a repository-relative path to the title of a page that belongs
+    /// in changelogs
+    ///
+    /// Returns `None` for paths that aren't pages, match an `ignore_pages`
+    /// pattern, or are excluded by `.svlmdignore`.
+    fn changelog_page_title(&self, path: &str, ignored: &[Pattern]) -> Option<String> {
+        let title = page_title_from_path(path, self.pages_dir_name())?;
+        let relative = path
+            .strip_prefix(self.pages_dir_name())?
+            .strip_prefix('/')?;
+        if ignored.iter().any(|pattern| pattern.matches(relative)) || self.ignore.is_ignored(path) {
+            return None;
+        }
+        Some(title)
+    }
+
+    /// Get the author of the last commit touching each page
+    ///
+    /// Walks the history from HEAD, newest first. Pages that no commit
+    /// touched, such as new pages, are missing from the result.
+    pub fn last_authors(&self, titles: &[String]) -> Result<HashMap<String, String>> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let mut authors = HashMap::new();
+        let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) else {
+            return Ok(authors);
+        };
+        let mut pending = titles
+            .iter()
+            .map(|title| (self.page_repo_path(title), title.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut revwalk = repo.revwalk().context("Failed to walk history")?;
+        revwalk.push(head.id())?;
+        revwalk.set_sorting(git2::Sort::TIME)?;
+        for oid in revwalk {
+            if pending.is_empty() {
+                break;
+            }
+            let commit = repo.find_commit(oid?)?;
+            let parent_tree = commit
+                .parents()
+                .next()
+                .map(|parent| parent.tree())
+                .transpose()?;
+            let diff = repo
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)
+                .with_context(|| format!("Failed to diff commit {}", commit.id()))?;
+            for delta in diff.deltas() {
+                let paths = [delta.old_file().path(), delta.new_file().path()];
+                for path in paths.into_iter().flatten().filter_map(Path::to_str) {
+                    if let Some(title) = pending.remove(path) {
+                        let author = commit.author().name().unwrap_or_default().to_string();
+                        authors.insert(title, author);
+                    }
+                }
+            }
+        }
+        Ok(authors)
+    }
+
+    /// Get the names of the authors of commits touching pages, sorted
+    pub fn page_authors(&self) -> Result<Vec<String>> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let mut authors = BTreeSet::new();
+        let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) else {
+            return Ok(vec![]);
+        };
+        let pages_prefix = format!("{}/", self.pages_dir_name());
+
+        let mut revwalk = repo.revwalk().context("Failed to walk history")?;
+        revwalk.push(head.id())?;
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            let Some(author) = commit.author().name().map(str::trim).map(String::from) else {
+                continue;
+            };
+            if author.is_empty() || authors.contains(&author) {
+                continue;
+            }
+            let parent_tree = commit
+                .parents()
+                .next()
+                .map(|parent| parent.tree())
+                .transpose()?;
+            let diff = repo
+                .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)
+                .with_context(|| format!("Failed to diff commit {}", commit.id()))?;
+            let touches_pages = diff.deltas().any(|delta| {
+                [delta.old_file().path(), delta.new_file().path()]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Path::to_str)
+                    .any(|path| path.starts_with(&pages_prefix))
+            });
+            if touches_pages {
+                authors.insert(author);
+            }
+        }
+        Ok(authors.into_iter().collect())
+    }
+
+    /// Get the pages changed between two commits
+    ///
+    /// Compares the trees of `old` and `new`, or the empty tree and `new` when
+    /// `old` is `None`.
+    pub fn get_changed_pages_between(&self, old: Option<Oid>, new: Oid) -> Result<ChangedPages> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let tree_of = |oid: Oid| -> Result<git2::Tree> {
+            Ok(repo
+                .find_commit(oid)
+                .with_context(|| format!("Failed to find commit {}", oid))?
+                .tree()?)
+        };
+        let old_tree = old.map(tree_of).transpose()?;
+        let new_tree = tree_of(new)?;
+
+        let diff = repo
+            .diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)
+            .context("Failed to diff commits")?;
+        let ignored = self.ignore_patterns()?;
+
+        let mut new_pages = Vec::new();
+        let mut modified_pages = Vec::new();
+        let mut deleted_pages = Vec::new();
+
+        for delta in diff.deltas() {
+            let path = match delta.status() {
+                Delta::Deleted => delta.old_file().path(),
+                _ => delta.new_file().path(),
+            };
+            let Some(page_name) =
+                path.and_then(|path| self.changelog_page_title(path.to_str()?, &ignored))
+            else {
+                continue;
+            };
+            match delta.status() {
+                Delta::Added => new_pages.push(page_name),
+                Delta::Modified | Delta::Renamed => modified_pages.push(page_name),
+                Delta::Deleted => deleted_pages.push(page_name),
+                _ => {}
+            }
+        }
+
+        Ok(ChangedPages {
+            added: new_pages,
+            modified: modified_pages,
+            deleted: deleted_pages,
+            conflicted: Vec::new(),
+        })
+    }
+}
+
+/// Sort properties with those in `order` first, then alphabetically by key
+fn sort_properties(properties: &mut [(String, String)], order: &[String]) {
+    properties.sort_by(|(a, _), (b, _)| {
+        let rank = |key: &String| order.iter().position(|k| k == key).unwrap_or(order.len());
+        rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+    });
+}
+
+/// Check if a staged modification only changes formatting or property order
+///
+/// Parses the page as committed in HEAD and as staged in the index, and
+/// compares the results.
+fn is_formatting_change(repo: &Repository, path: &str, title: &str) -> Result<bool> {
+    let Ok(head_tree) = repo.head().and_then(|head| head.peel_to_tree()) else {
+        return Ok(false);
+    };
+    let Ok(head_entry) = head_tree.get_path(Path::new(path)) else {
+        return Ok(false);
+    };
+    let Some(index_entry) = repo.index()?.get_path(Path::new(path), 0) else {
+        return Ok(false);
+    };
+
+    let parse_blob = |oid: Oid| -> Result<LogseqPage> {
+        let blob = repo.find_blob(oid)?;
+        let lines = String::from_utf8_lossy(blob.content())
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        Ok(LogseqPage::parse(title, &lines))
+    };
+    let committed = parse_blob(head_entry.id())?;
+    let staged = parse_blob(index_entry.id)?;
+    Ok(committed.is_equivalent(&staged))
+}
+
+/// Pages rewritten by renaming a page, as planned by
+/// `FileManager::plan_rename`
+pub struct PageRename {
+    /// Title of the renamed page as stored on disk
+    pub from: String,
+    /// New title of the page
+    pub to: String,
+    /// Pages to write, with the lines changed in each. The renamed page is
+    /// included even if none of its lines changed.
+    pub pages: Vec<(LogseqPage, Vec<RenamedLine>)>,
+}
+
+impl PageRename {
+    /// Number of links and tags renamed
+    pub fn references(&self) -> usize {
+        self.pages
+            .iter()
+            .flat_map(|(_, changed)| changed)
+            .map(|line| line.references)
+            .sum()
+    }
+
+    /// Number of pages with renamed links or tags
+    pub fn pages_updated(&self) -> usize {
+        self.pages
+            .iter()
+            .filter(|(_, changed)| !changed.is_empty())
+            .count()
+    }
+}
+
+/// Pages rewritten by renaming several pages at once, as planned by
+/// `FileManager::plan_batch_rename`
+pub struct BatchRename {
+    /// Titles of the renamed pages as stored on disk, and their new titles,
+    /// in the order of the rename map
+    pub renames: Vec<(String, String)>,
+    /// Number of links and tags renamed for each entry of `renames`
+    pub references: Vec<usize>,
+    /// Pages to write, under their new titles, with the number of lines
+    /// changed in each. Renamed pages are included even if none of their
+    /// lines changed.
+    pub pages: Vec<(LogseqPage, usize)>,
+}
+
+impl BatchRename {
+    /// Number of pages with renamed links or tags
+    pub fn pages_updated(&self) -> usize {
+        self.pages
+            .iter()
+            .filter(|(_, changed)| *changed > 0)
+            .count()
+    }
+}
+
+/// A git tag marking a released version
+pub struct VersionTag {
+    /// Version parsed from the tag name
+    pub version: semver::Version,
+    /// Commit the tag points at
+    pub commit: Oid,
+    /// Commit time in seconds since the Unix epoch
+    pub time: i64,
+}
+
+impl FileManager {
+    /// Get all `v*` version tags, sorted by semver
+    ///
+    /// Tags whose names don't parse as semver are skipped with a warning.
+    pub fn version_tags(&self) -> Result<Vec<VersionTag>> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let mut tags = Vec::new();
+        for name in repo.tag_names(Some("v*"))?.iter().flatten() {
+            let Ok(version) = semver::Version::parse(&name[1..]) else {
+                eprintln!("Warning: skipping tag {}: not a semver version", name);
+                continue;
+            };
+            let commit = repo
+                .revparse_single(&format!("refs/tags/{}", name))?
+                .peel_to_commit()
+                .with_context(|| format!("Tag {} doesn't point at a commit", name))?;
+            tags.push(VersionTag {
+                version,
+                commit: commit.id(),
+                time: commit.time().seconds(),
+            });
+        }
+        tags.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(tags)
+    }
+
+    /// Get the subject lines of the commits reachable from HEAD but not from
+    /// `since`, oldest first
+    ///
+    /// All commits are included if `since` is `None`. Merge commits are
+    /// skipped.
+    pub fn commit_subjects(&self, since: Option<Oid>) -> Result<Vec<String>> {
+        let repo = Repository::open(&self.root).context("Failed to open git repository")?;
+        let mut revwalk = repo.revwalk().context("Failed to walk history")?;
+        revwalk.push_head().context("Failed to read HEAD")?;
+        if let Some(since) = since {
+            revwalk.hide(since)?;
+        }
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+
+        let mut subjects = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            if let Some(subject) = commit.summary().map(str::trim) {
+                if !subject.is_empty() {
+                    subjects.push(subject.to_string());
+                }
+            }
+        }
+        Ok(subjects)
+    }
+}
+
+/// Convert a repository-relative path to a page title
+///
+/// Returns `None` if the path isn't a page directly under `pages_dir`.
+fn page_title_from_path(path: &str, pages_dir: &str) -> Option<String> {
+    let filename = path
+        .strip_prefix(pages_dir)?
+        .strip_prefix('/')?
+        .strip_suffix(".md")?;
+    Some(filename.replace("___", "/"))
+}
+
+/// Returns the path to the current executable
+pub fn get_executable_path() -> Result<PathBuf> {
+    std::env::current_exe().map_err(|e| anyhow::anyhow!("Failed to get executable path: {}", e))
+}
+
+/// Resolves the root directory of the project
+///
+/// An explicit `root` takes precedence over auto-detection. It must contain a
+/// `.svlmd` configuration file or a `pages/` directory, and is canonicalized
+/// like a detected root.
+pub fn resolve_root(root: Option<&Path>) -> Result<PathBuf> {
+    let Some(root) = root else {
+        return detect_root();
+    };
+    if !root.join(".svlmd").exists() && !root.join("pages/").exists() {
+        anyhow::bail!(
+            "{} is not an SVLMD project: no .svlmd or pages/ found",
+            root.display()
+        );
+    }
+    root.canonicalize()
+        .with_context(|| format!("Failed to resolve {}", root.display()))
+}
+
+/// Detects the root directory of the project
+///
+/// Searches for the .svlmd configuration file to determine
+/// the root directory of the SVLMD project. The returned path is
+/// canonicalized, so symlinks along the way are resolved to the real path.
+pub fn detect_root() -> Result<PathBuf> {
+    let root = find_root()?;
+    root.canonicalize()
+        .with_context(|| format!("Failed to resolve {}", root.display()))
+}
+
+/// Finds the root directory relative to the executable location
+fn find_root() -> Result<PathBuf> {
+    let exe_path = get_executable_path()?;
+    fn fail() -> anyhow::Error {
+        anyhow::anyhow!("Failed to get executable directory")
+    }
+    // Get the directory containing the executable
+    let exe_dir = exe_path.parent().ok_or_else(fail)?;
+
+    // Check if we're in debug/release mode (in target directory)
+    if exe_dir.ends_with("debug") || exe_dir.ends_with("release") {
+        // Go up three levels: debug/release -> target -> project_root
+        return Ok(exe_dir
+            .parent() // target
+            .ok_or_else(fail)?
+            .parent() // svlmd
+            .ok_or_else(fail)?
+            .parent() // root
+            .ok_or_else(fail)?
+            .to_path_buf());
+    }
+
+    // If we're running the installed binary, check current directory.
+    // `exists` follows symlinks, so a symlinked pages directory is accepted.
+    if exe_dir.join("pages/").exists() {
+        return Ok(exe_dir.to_path_buf());
+    }
+
+    anyhow::bail!("Failed to detect root directory. Please run svlmd from project root or installation directory.")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Create a vault with an empty pages directory in a temporary directory
+    ///
+    /// `config` holds `.svlmd` keys on top of a contributor named `Tester`.
+    /// The user-level configuration isn't read, so results don't depend on
+    /// the machine running the tests.
+    pub(crate) fn vault(config: serde_json::Value) -> (tempfile::TempDir, FileManager) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut keys = serde_json::json!({ "contributor": "Tester" });
+        keys.as_object_mut()
+            .unwrap()
+            .extend(config.as_object().cloned().unwrap_or_default());
+        let file_manager = FileManager {
+            root: dir.path().to_path_buf(),
+            config: serde_json::from_value(keys).unwrap(),
+            ignore: IgnoreRules::default(),
+            writer: Arc::new(Disk),
+        };
+        fs::create_dir(file_manager.pages_dir()).unwrap();
+        (dir, file_manager)
+    }
+
+    /// Create a git repository for a vault, committing nothing
+    pub(crate) fn init_git(file_manager: &FileManager) -> Repository {
+        file_manager.init_repository().unwrap();
+        Repository::open(&file_manager.root).unwrap()
+    }
+
+    /// Write a file relative to the pages directory, creating directories
+    fn write_pages_file(file_manager: &FileManager, path: &str, text: &str) {
+        let path = file_manager.pages_dir().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    /// Write a page file as raw text
+    pub(crate) fn write_page_file(file_manager: &FileManager, title: &str, text: &str) {
+        let page = LogseqPage::new(title, vec![], vec![]);
+        fs::write(page.title_to_path(&file_manager.pages_dir()), text).unwrap();
+    }
+
+    /// Build a bullet line indented by `level` levels
+    fn bullet(level: usize, text: &str) -> String {
+        format!("{}- {}", "    ".repeat(level), text)
+    }
+
+    #[test]
+    fn indentation_at_u8_max_is_kept() {
+        assert_eq!(indentation_level(&bullet(255, "deep")), Ok(255));
+
+        let page = LogseqPage::from_plain("Deep", vec![], &bullet(255, "deep"));
+        assert_eq!(page.contents, vec![("deep".to_string(), 255)]);
+    }
+
+    #[test]
+    fn indentation_above_u8_max_is_clamped() {
+        // The overflowing level is what the warning reports
+        assert_eq!(indentation_level(&bullet(256, "deeper")), Err(256));
+        assert_eq!(indentation_level(&bullet(1000, "deeper")), Err(1000));
+
+        let contents = [bullet(0, "top"), bullet(256, "deeper")].join("\n");
+        let page = LogseqPage::from_plain("Deep", vec![], &contents);
+        assert_eq!(
+            page.contents,
+            vec![("top".to_string(), 0), ("deeper".to_string(), 255)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_pages_directory_and_files_are_followed() {
+        use std::os::unix::fs::symlink;
+
+        let (dir, file_manager) = vault(serde_json::json!({}));
+        let storage = dir.path().join("storage");
+        fs::create_dir(&storage).unwrap();
+        fs::remove_dir(file_manager.pages_dir()).unwrap();
+        symlink(&storage, file_manager.pages_dir()).unwrap();
+        write_page_file(&file_manager, "Aspirin", "tags:: Drug\n\n- text\n");
+        let target = dir.path().join("Metformin.md");
+        fs::write(&target, "- text\n").unwrap();
+        symlink(&target, storage.join("Metformin.md")).unwrap();
+        symlink(dir.path().join("missing.md"), storage.join("Broken.md")).unwrap();
+
+        assert_eq!(file_manager.list_pages().unwrap(), ["Aspirin", "Metformin"]);
+        assert!(file_manager.logseq_page_exists("Metformin"));
+        assert!(!file_manager.logseq_page_exists("Broken"));
+        let page = file_manager.read_logseq_page("Aspirin").unwrap();
+        assert_eq!(page.tags(), ["Drug"]);
+    }
+
+    #[test]
+    fn case_insensitive_lookup_finds_the_stored_title() {
+        let (_dir, file_manager) = vault(serde_json::json!({ "case_insensitive_pages": true }));
+        write_page_file(&file_manager, "drug", "- original\n");
+
+        assert!(file_manager.logseq_page_exists("Drug"));
+        assert_eq!(
+            file_manager.find_page_title("DRUG").as_deref(),
+            Some("drug")
+        );
+        assert_eq!(file_manager.find_page_title("Drugs"), None);
+    }
+
+    // Case-insensitive filesystems find the file whatever the setting
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    #[test]
+    fn case_sensitive_lookup_ignores_other_cases() {
+        let (_dir, file_manager) = vault(serde_json::json!({ "case_insensitive_pages": false }));
+        write_page_file(&file_manager, "drug", "- original\n");
+
+        assert!(file_manager.logseq_page_exists("drug"));
+        assert!(!file_manager.logseq_page_exists("Drug"));
+    }
+
+    #[test]
+    fn config_without_optional_fields_uses_defaults() {
+        let (dir, file_manager) = vault(serde_json::json!({}));
+
+        assert_eq!(file_manager.contributor(), "Tester");
+        assert_eq!(file_manager.contributor_email(), None);
+        assert_eq!(file_manager.icon(), DEFAULT_ICON);
+        assert_eq!(file_manager.date_format(), "%Y-%m-%d");
+        assert_eq!(file_manager.pages_dir(), dir.path().join("pages"));
+        assert_eq!(file_manager.version_source().to_string(), "version.txt");
+        assert!(!file_manager.version_source_configured());
+        assert!(file_manager.required_tags().is_empty());
+        assert_eq!(
+            file_manager.changelog_headings(),
+            ChangelogHeadings::default()
+        );
+        assert_eq!(file_manager.changelog_links(), ChangelogLinks::Title);
+        assert_eq!(file_manager.changelog_order(), ChangelogOrder::Title);
+        assert_eq!(file_manager.property_style(), PropertyStyle::Spaced);
+        assert_eq!(file_manager.max_changed_pages(), None);
+        assert_eq!(file_manager.git_retries(), 2);
+        let time = DateTime::parse_from_rfc3339("2024-03-01T23:30:00Z").unwrap();
+        assert_eq!(file_manager.format_date(time.into()).unwrap(), "2024-03-01");
+    }
+
+    #[test]
+    fn insert_under_heading_nests_relative_to_the_heading() {
+        let mut page = LogseqPage::from_plain("1.2.0", vec![], "- # Summary\n    - ## Notes");
+        page.insert_under_heading("## Notes", ("Reviewed".into(), 1))
+            .unwrap();
+        assert_eq!(
+            page.contents,
+            [
+                ("# Summary".to_string(), 0),
+                ("## Notes".to_string(), 1),
+                ("Reviewed".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_under_heading_uses_the_first_matching_heading() {
+        let mut page = LogseqPage::from_plain("1.2.0", vec![], "- # Summary\n- a\n- # Summary");
+        page.insert_under_heading("# Summary", ("First".into(), 1))
+            .unwrap();
+        assert_eq!(
+            page.contents,
+            [
+                ("# Summary".to_string(), 0),
+                ("First".to_string(), 1),
+                ("a".to_string(), 0),
+                ("# Summary".to_string(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_under_missing_heading_fails() {
+        let mut page = LogseqPage::from_plain("1.2.0", vec![], "- # Changed Pages");
+        let before = page.clone();
+
+        let error = page
+            .insert_under_heading("# Summary", ("Text".into(), 1))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "1.2.0 has no '# Summary' heading");
+        assert_eq!(page, before);
+    }
+
+    #[test]
+    fn ignored_pages_are_left_out_of_change_detection() {
+        let (_dir, file_manager) = vault(serde_json::json!({
+            "email": "tester@example.com",
+            "ignore_pages": ["_templates/*", "scratch*"],
+        }));
+        init_git(&file_manager);
+        let files = ["Aspirin.md", "_templates/Drug.md", "scratch-1.md"];
+        for file in files {
+            write_pages_file(&file_manager, file, "- first\n");
+        }
+        let paths = files.map(|file| format!("pages/{}", file));
+        file_manager.commit(&paths, "Add pages").unwrap();
+
+        for file in files {
+            write_pages_file(&file_manager, file, "- second\n");
+        }
+        write_pages_file(&file_manager, "_templates/Symptom.md", "- new\n");
+        write_pages_file(&file_manager, "Metformin.md", "- new\n");
+        let mut staged = paths.to_vec();
+        staged.push("pages/_templates/Symptom.md".into());
+        staged.push("pages/Metformin.md".into());
+        file_manager.stage(&staged).unwrap();
+
+        let changes = file_manager
+            .get_changed_pages(&ChangeOptions::default())
+            .unwrap();
+        assert_eq!(changes.added, ["Metformin"]);
+        assert_eq!(changes.modified, ["Aspirin"]);
+        assert!(changes.deleted.is_empty());
+    }
+
+    #[test]
+    fn empty_bullets_and_blank_lines_roundtrip() {
+        let text = "- a\n-\n    -\n\n- b\n  \n- c\n";
+        let page = LogseqPage::from_plain("Page", vec![], text);
+        assert_eq!(
+            page.contents,
+            [
+                ("a".to_string(), 0),
+                (EMPTY_BLOCK.to_string(), 0),
+                (EMPTY_BLOCK.to_string(), 1),
+                (String::new(), 0),
+                ("b".to_string(), 0),
+                (String::new(), 0),
+                ("c".to_string(), 0),
+            ]
+        );
+        assert!(is_blank(&page.contents[1].0) && is_blank(&page.contents[3].0));
+
+        // A line of only whitespace is written back as an empty line
+        let written = page.to_text(PropertyStyle::Spaced);
+        assert_eq!(written, "- a\n-\n    -\n\n- b\n\n- c\n");
+        let lines = written.lines().map(String::from).collect::<Vec<_>>();
+        assert_eq!(LogseqPage::parse("Page", &lines), page);
+    }
+
+    #[test]
+    fn property_values_keep_later_double_colons() {
+        assert_eq!(
+            parse_property("url:: http://a::b"),
+            Some(("url".to_string(), "http://a::b".to_string()))
+        );
+        assert_eq!(
+            parse_property("source::https://example.com::8080/x"),
+            Some((
+                "source".to_string(),
+                "https://example.com::8080/x".to_string()
+            ))
+        );
+
+        let lines = [
+            "url:: http://a::b".to_string(),
+            String::new(),
+            "- text".to_string(),
+        ];
+        let page = LogseqPage::parse("Page", &lines);
+        assert_eq!(page.property("url"), Some("http://a::b"));
+        assert_eq!(
+            page.to_text(PropertyStyle::Spaced),
+            "url:: http://a::b\n\n- text\n"
+        );
+    }
+
+    #[test]
+    fn prepare_write_sorts_a_copy_of_the_page() {
+        let (_dir, file_manager) = vault(serde_json::json!({
+            "sort_properties": true,
+            "property_order": ["tags"],
+        }));
+        let page = LogseqPage::new(
+            "Aspirin",
+            vec![
+                ("icon".into(), "💊".into()),
+                ("tags".into(), "Drug".into()),
+                ("alias".into(), "ASA".into()),
+            ],
+            vec![("text".into(), 0)],
+        );
+        let original = page.clone();
+
+        let prepared = file_manager.prepare_write(&page);
+        assert_eq!(page, original);
+        assert_eq!(
+            prepared,
+            LogseqPage::new(
+                "Aspirin",
+                vec![
+                    ("tags".into(), "Drug".into()),
+                    ("alias".into(), "ASA".into()),
+                    ("icon".into(), "💊".into()),
+                ],
+                vec![("text".into(), 0)],
+            )
+        );
+        assert!(prepared.is_equivalent(&page));
+    }
+
+    /// A page as Logseq writes it when the properties were added to its first
+    /// block
+    const FIRST_BLOCK_PROPERTIES: &str =
+        "\n- tags:: Drug\n  alias:: ASA\n\n- Dosage\n    - 300 mg\n";
+
+    /// Split a fixture into the lines read from a page file
+    fn fixture_lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn first_block_of_properties_is_read_as_page_properties() {
+        let page = LogseqPage::parse("Aspirin", &fixture_lines(FIRST_BLOCK_PROPERTIES));
+        assert_eq!(
+            page.properties,
+            [
+                ("tags".to_string(), "Drug".to_string()),
+                ("alias".to_string(), "ASA".to_string()),
+            ]
+        );
+        assert_eq!(
+            page.contents,
+            [("Dosage".to_string(), 0), ("300 mg".to_string(), 1)]
+        );
+        // Written back as a header
+        assert_eq!(
+            page.to_text(PropertyStyle::Spaced),
+            "tags:: Drug\nalias:: ASA\n\n- Dosage\n    - 300 mg\n"
+        );
+    }
+
+    #[test]
+    fn first_block_with_text_or_children_is_content() {
+        let with_text = "- Dosage\n  id:: 6500a1b2-3c4d-4e5f-8a9b-0c1d2e3f4a5b\n";
+        let page = LogseqPage::parse("Aspirin", &fixture_lines(with_text));
+        assert!(page.properties.is_empty());
+        assert_eq!(page.block_ids(), ["6500a1b2-3c4d-4e5f-8a9b-0c1d2e3f4a5b"]);
+
+        let with_children = "- tags:: Drug\n    - child\n";
+        let page = LogseqPage::parse("Aspirin", &fixture_lines(with_children));
+        assert!(page.properties.is_empty());
+        assert_eq!(
+            page.contents,
+            [("tags:: Drug".to_string(), 0), ("child".to_string(), 1)]
+        );
+    }
+
+    /// Build an index entry for a blob at a repository-relative path, at a
+    /// merge stage (1 for the ancestor, 2 for ours, 3 for theirs)
+    fn index_entry(repo: &Repository, path: &str, stage: u16, text: &str) -> git2::IndexEntry {
+        git2::IndexEntry {
+            ctime: git2::IndexTime::new(0, 0),
+            mtime: git2::IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: text.len() as u32,
+            id: repo.blob(text.as_bytes()).unwrap(),
+            flags: stage << 12,
+            flags_extended: 0,
+            path: path.as_bytes().to_vec(),
+        }
+    }
+
+    /// Leave a file in the index as a merge would after conflicting edits
+    pub(crate) fn add_conflict(repo: &Repository, path: &str) {
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new(path)).unwrap();
+        for (stage, text) in [(1, "- base\n"), (2, "- ours\n"), (3, "- theirs\n")] {
+            index.add(&index_entry(repo, path, stage, text)).unwrap();
+        }
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn conflicted_pages_are_reported_separately() {
+        let (_dir, file_manager) = vault(serde_json::json!({ "email": "tester@example.com" }));
+        let repo = init_git(&file_manager);
+        write_pages_file(&file_manager, "Aspirin.md", "- base\n");
+        write_pages_file(&file_manager, "Metformin.md", "- base\n");
+        let paths = [
+            "pages/Aspirin.md".to_string(),
+            "pages/Metformin.md".to_string(),
+        ];
+        file_manager.commit(&paths, "Add pages").unwrap();
+
+        add_conflict(&repo, "pages/Aspirin.md");
+        write_pages_file(&file_manager, "Metformin.md", "- edited\n");
+        file_manager.stage(&paths[1..]).unwrap();
+
+        let changes = file_manager
+            .get_changed_pages(&ChangeOptions::default())
+            .unwrap();
+        assert_eq!(changes.conflicted, ["Aspirin"]);
+        assert_eq!(changes.modified, ["Metformin"]);
+        assert!(changes.added.is_empty() && changes.deleted.is_empty());
+    }
+
+    #[test]
+    fn resolve_rejects_paths_outside_the_root() {
+        let (dir, file_manager) = vault(serde_json::json!({}));
+
+        for path in [
+            "../etc/passwd",
+            "a/../../etc/passwd",
+            "~/notes.txt",
+            "~",
+            "/etc/passwd",
+        ] {
+            assert!(file_manager.resolve(path).is_err(), "{} was resolved", path);
+        }
+        let error = file_manager.resolve("../etc/passwd").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Path ../etc/passwd leads outside the project root"
+        );
+        assert!(file_manager.read_to_string("../etc/passwd").is_err());
+        assert!(file_manager.write("../escaped.txt", "x").is_err());
+        assert!(!dir.path().parent().unwrap().join("escaped.txt").exists());
+
+        assert_eq!(
+            file_manager.resolve("sub/../version.txt").unwrap(),
+            dir.path().join("sub/../version.txt")
+        );
+        assert_eq!(
+            file_manager.resolve("./VERSION").unwrap(),
+            dir.path().join("./VERSION")
+        );
+    }
+
+    #[test]
+    fn pages_directory_is_created_on_first_write() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        fs::remove_dir(file_manager.pages_dir()).unwrap();
+
+        let page = LogseqPage::new(
+            "Aspirin",
+            vec![],
+            vec![("An antiplatelet drug".to_string(), 0)],
+        );
+        file_manager.write_logseq_page(&page).unwrap();
+
+        assert!(file_manager.pages_dir().is_dir());
+        let written = file_manager.read_logseq_page("Aspirin").unwrap();
+        assert_eq!(written.contents, page.contents);
+    }
+
+    #[test]
+    fn rename_counts_every_rewritten_reference() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        write_page_file(&file_manager, "Aspirin", "- See also [[aspirin]]\n");
+        write_page_file(
+            &file_manager,
+            "Notes",
+            "tags:: Aspirin, Drug\n\n- [[Aspirin]] and [[ aspirin ]]\n- [[Metformin]]\n",
+        );
+        write_page_file(&file_manager, "Metformin", "- [[Notes]]\n");
+
+        let references = file_manager
+            .rename_logseq_page("Aspirin", "Acetylsalicylic acid", true)
+            .unwrap();
+
+        assert_eq!(references, 4);
+        assert!(!file_manager.logseq_page_exists("Aspirin"));
+        let notes = file_manager.read_logseq_page("Notes").unwrap();
+        assert_eq!(
+            notes.properties,
+            vec![("tags".to_string(), "Acetylsalicylic acid, Drug".to_string())]
+        );
+        assert_eq!(
+            notes.contents[0].0,
+            "[[Acetylsalicylic acid]] and [[Acetylsalicylic acid]]"
+        );
+        let renamed = file_manager
+            .read_logseq_page("Acetylsalicylic acid")
+            .unwrap();
+        assert_eq!(renamed.contents[0].0, "See also [[Acetylsalicylic acid]]");
+    }
+
+    #[test]
+    fn rename_without_rewriting_links_counts_nothing() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        write_page_file(&file_manager, "Aspirin", "- text\n");
+        write_page_file(&file_manager, "Notes", "- [[Aspirin]]\n");
+        write_page_file(&file_manager, "Metformin", "- text\n");
+
+        assert_eq!(
+            file_manager
+                .rename_logseq_page("Aspirin", "Acetylsalicylic acid", false)
+                .unwrap(),
+            0
+        );
+        let notes = file_manager.read_logseq_page("Notes").unwrap();
+        assert_eq!(notes.contents[0].0, "[[Aspirin]]");
+
+        let error = file_manager
+            .rename_logseq_page("Acetylsalicylic acid", "Metformin", true)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Page 'Metformin' already exists");
+    }
+
+    #[test]
+    fn resolve_page_accepts_titles_filenames_and_aliases() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        write_page_file(
+            &file_manager,
+            "Drug/Aspirin",
+            "alias:: ASA, Acetylsalicylic acid\n\n- text\n",
+        );
+        write_page_file(&file_manager, "Metformin", "- text\n");
+
+        for name in [
+            "Drug/Aspirin",
+            " Drug/Aspirin ",
+            "Drug___Aspirin.md",
+            "pages/Drug___Aspirin.md",
+            "ASA",
+            "acetylsalicylic acid",
+        ] {
+            assert_eq!(
+                file_manager.resolve_page(name).unwrap(),
+                "Drug/Aspirin",
+                "{} wasn't resolved",
+                name
+            );
+        }
+        assert_eq!(
+            file_manager.resolve_page("Metformin.md").unwrap(),
+            "Metformin"
+        );
+    }
+
+    #[test]
+    fn resolve_page_reports_unknown_pages() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        write_page_file(&file_manager, "Metformin", "- text\n");
+
+        let error = file_manager.resolve_page("Ibuprofen").unwrap_err();
+        assert_eq!(error.to_string(), "Page 'Ibuprofen' not found");
+        assert!(file_manager.resolve_page("Ibuprofen.md").is_err());
+    }
+
+    #[test]
+    fn page_path_refuses_titles_leading_outside_the_pages_directory() {
+        let (dir, file_manager) = vault(serde_json::json!({}));
+
+        for title in [
+            "../x",
+            "../../evil",
+            "/abs",
+            "a/../../b",
+            "./x",
+            "a//b",
+            r"sub\dir",
+            r"..\x",
+        ] {
+            let error = file_manager.page_path(title).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .starts_with(&format!("Refusing to write page '{}' outside", title)),
+                "{}",
+                error
+            );
+        }
+        let page = LogseqPage::new("../../evil", vec![], vec![]);
+        assert!(file_manager.write_logseq_page(&page).is_err());
+        assert!(!dir.path().parent().unwrap().join("evil.md").exists());
+    }
+
+    #[test]
+    fn page_path_keeps_plain_and_namespaced_titles_flat() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        let pages_dir = file_manager.pages_dir();
+
+        assert_eq!(
+            file_manager.page_path("Aspirin").unwrap(),
+            pages_dir.join("Aspirin.md")
+        );
+        // A `/` separates namespaces, not directories
+        assert_eq!(
+            file_manager.page_path("sub/dir").unwrap(),
+            pages_dir.join("sub___dir.md")
+        );
+        assert_eq!(
+            file_manager.page_path("Drug/Aspirin 1.5").unwrap(),
+            pages_dir.join("Drug___Aspirin 1.5.md")
+        );
+    }
+
+    /// Build a git error of a lock held by another process
+    fn locked() -> git2::Error {
+        git2::Error::new(
+            git2::ErrorCode::Locked,
+            git2::ErrorClass::Index,
+            "index is locked",
+        )
+    }
+
+    #[test]
+    fn retry_git_retries_transient_errors() {
+        let mut calls = 0;
+        let result = retry_git(3, || {
+            calls += 1;
+            if calls <= 2 {
+                Err(locked())
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: Result<(), git2::Error> = retry_git(1, || {
+            calls += 1;
+            Err(locked())
+        });
+        assert_eq!(result.unwrap_err().code(), git2::ErrorCode::Locked);
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn retry_git_returns_other_errors_at_once() {
+        let mut calls = 0;
+        let result: Result<(), git2::Error> = retry_git(3, || {
+            calls += 1;
+            Err(git2::Error::new(
+                git2::ErrorCode::NotFound,
+                git2::ErrorClass::Repository,
+                "repository not found",
+            ))
+        });
+        assert_eq!(result.unwrap_err().code(), git2::ErrorCode::NotFound);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn compact_property_style_roundtrips_byte_for_byte() {
+        let text = "tags::Drug\nsource::[[Label]], leaflet\n\n- Dosage\n    - 500 mg\n";
+        let (_dir, file_manager) = vault(serde_json::json!({ "property_style": "compact" }));
+        write_page_file(&file_manager, "Aspirin", text);
+
+        let page = file_manager.read_logseq_page("Aspirin").unwrap();
+        file_manager.write_logseq_page(&page).unwrap();
+
+        let path = file_manager.page_path("Aspirin").unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), text);
+    }
+
+    #[test]
+    fn spaced_property_style_normalizes_compact_properties() {
+        let text = "tags::Drug\nsource::[[Label]], leaflet\n\n- Dosage\n    - 500 mg\n";
+        let (_dir, file_manager) = vault(serde_json::json!({ "property_style": "spaced" }));
+        write_page_file(&file_manager, "Aspirin", text);
+
+        let page = file_manager.read_logseq_page("Aspirin").unwrap();
+        file_manager.write_logseq_page(&page).unwrap();
+
+        let path = file_manager.page_path("Aspirin").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "tags:: Drug\nsource:: [[Label]], leaflet\n\n- Dosage\n    - 500 mg\n"
+        );
+        // Once normalized, the page is written back unchanged
+        let page = file_manager.read_logseq_page("Aspirin").unwrap();
+        let before = fs::read_to_string(&path).unwrap();
+        file_manager.write_logseq_page(&page).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+    }
 }
@@ -0,0 +1,212 @@
+/// `.svlmdignore` rules
+///
+/// The `.svlmdignore` file at the project root lists pages to leave out of
+/// change detection, validation, listing and export, one gitignore-style
+/// pattern per line:
+/// - Blank lines and lines starting with `#` are skipped
+/// - A pattern without a `/` matches a file or directory name at any depth,
+///   e.g. `scratch*.md`
+/// - A pattern with a `/` is relative to the project root, e.g.
+///   `pages/_templates/*`. A leading `/` only anchors the pattern.
+/// - A trailing `/` matches directories only
+/// - A leading `!` re-includes paths excluded by an earlier pattern
+///
+/// The last matching pattern wins. Patterns use the `*`, `?`, `[...]` and
+/// `**` syntax of the `glob` crate, with `*` not crossing `/`.
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+use std::{fs, io, path::Path};
+
+/// Name of the ignore file at the project root
+pub const IGNORE_FILE: &str = ".svlmdignore";
+
+/// Options matching patterns like gitignore does
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// A pattern of the ignore file
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    /// Whether the pattern re-includes paths (`!pattern`)
+    negated: bool,
+    /// Whether the pattern is matched against the path from the root,
+    /// rather than against each name in it
+    anchored: bool,
+    /// Whether the pattern matches directories only (`pattern/`)
+    dir_only: bool,
+}
+
+impl Rule {
+    /// Check whether the rule matches a root-relative path or one of the
+    /// directories containing it
+    fn matches(&self, path: &str) -> bool {
+        let mut prefixes = path
+            .match_indices('/')
+            .map(|(end, _)| &path[..end])
+            .collect::<Vec<_>>();
+        if !self.dir_only {
+            prefixes.push(path);
+        }
+        prefixes.iter().any(|prefix| {
+            let candidate = if self.anchored {
+                prefix
+            } else {
+                prefix.rsplit('/').next().unwrap_or(prefix)
+            };
+            self.pattern.matches_with(candidate, MATCH_OPTIONS)
+        })
+    }
+}
+
+/// Parsed rules of the ignore file
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Load the ignore file of the project at `root`
+    ///
+    /// A missing file ignores nothing.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(IGNORE_FILE);
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Parse the rules from the contents of an ignore file
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let line = line.strip_prefix('/').unwrap_or(line);
+            let pattern = Pattern::new(line).with_context(|| {
+                format!(
+                    "Invalid pattern '{}' on line {} of {}",
+                    line,
+                    i + 1,
+                    IGNORE_FILE
+                )
+            })?;
+            rules.push(Rule {
+                pattern,
+                negated,
+                anchored,
+                dir_only,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// Check whether a path, relative to the project root with `/`
+    /// separators, is ignored
+    pub fn is_ignored(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse rules from ignore file lines
+    fn rules(lines: &[&str]) -> IgnoreRules {
+        IgnoreRules::parse(&lines.join("\n")).unwrap()
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let rules = rules(&["# scratch.md", "", "   "]);
+        assert!(!rules.is_ignored("pages/scratch.md"));
+        assert!(!rules.is_ignored("# scratch.md"));
+    }
+
+    #[test]
+    fn unanchored_patterns_match_names_at_any_depth() {
+        let rules = rules(&["scratch*.md"]);
+        assert!(rules.is_ignored("scratch.md"));
+        assert!(rules.is_ignored("pages/scratch-1.md"));
+        assert!(rules.is_ignored("pages/drafts/scratch.md"));
+        assert!(!rules.is_ignored("pages/Aspirin.md"));
+        assert!(!rules.is_ignored("pages/my-scratch.md"));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored_to_the_root() {
+        let rules = rules(&["pages/_templates/*", "/drafts"]);
+        assert!(rules.is_ignored("pages/_templates/Drug.md"));
+        assert!(!rules.is_ignored("other/pages/_templates/Drug.md"));
+        // `*` doesn't cross `/`, but the directory matched holds its files
+        assert!(rules.is_ignored("pages/_templates/sub/Drug.md"));
+        assert!(rules.is_ignored("drafts"));
+        assert!(rules.is_ignored("drafts/Aspirin.md"));
+        assert!(!rules.is_ignored("pages/drafts/Aspirin.md"));
+    }
+
+    #[test]
+    fn trailing_slash_matches_directories_only() {
+        let rules = rules(&["archive/"]);
+        assert!(rules.is_ignored("pages/archive/Aspirin.md"));
+        assert!(rules.is_ignored("archive/old/Aspirin.md"));
+        assert!(!rules.is_ignored("pages/archive"));
+        assert!(!rules.is_ignored("archive"));
+    }
+
+    #[test]
+    fn negated_patterns_re_include_paths() {
+        let rules = rules(&["pages/Drug___*.md", "!pages/Drug___Aspirin.md"]);
+        assert!(rules.is_ignored("pages/Drug___Metformin.md"));
+        assert!(!rules.is_ignored("pages/Drug___Aspirin.md"));
+    }
+
+    #[test]
+    fn last_matching_pattern_wins() {
+        let rules = rules(&["*.md", "!Aspirin.md", "pages/Aspirin.md"]);
+        assert!(rules.is_ignored("pages/Aspirin.md"));
+        assert!(!rules.is_ignored("notes/Aspirin.md"));
+        assert!(rules.is_ignored("notes/Metformin.md"));
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported_with_their_line() {
+        let error = IgnoreRules::parse("scratch.md\n\n***\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid pattern '***' on line 3 of .svlmdignore"
+        );
+    }
+
+    #[test]
+    fn missing_ignore_file_ignores_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = IgnoreRules::load(dir.path()).unwrap();
+        assert!(!rules.is_ignored("pages/Aspirin.md"));
+
+        fs::write(dir.path().join(IGNORE_FILE), "Aspirin.md\n").unwrap();
+        let rules = IgnoreRules::load(dir.path()).unwrap();
+        assert!(rules.is_ignored("pages/Aspirin.md"));
+    }
+}
@@ -0,0 +1,353 @@
+/// Conversion of plain Markdown files to Logseq pages
+///
+/// YAML frontmatter becomes page properties. Only the simple forms common in
+/// frontmatter are understood: `key: value` scalars, optionally quoted, and
+/// lists written as `[a, b]` or as `- item` lines, which become
+/// comma-separated values.
+///
+/// The body becomes content blocks. Each heading, paragraph, list item and
+/// fenced code block is a block. Blocks under a heading are nested one level
+/// below it, as are headings of a deeper level, and list items are nested by
+/// their indentation. The lines of a paragraph or list item are joined with
+/// spaces, as Markdown renders them, while a code block keeps its lines,
+/// written under its bullet as Logseq does. A fence may also open on the
+/// line of a list item, as in Logseq's own Markdown.
+use crate::file_manager::LogseqPage;
+
+/// Convert the text of a Markdown file to a page
+pub fn from_markdown(title: &str, text: &str) -> LogseqPage {
+    let (properties, body) = split_frontmatter(text);
+    LogseqPage::new(title, properties, parse_blocks(body))
+}
+
+/// Split a file into its frontmatter properties and its body
+///
+/// Text without a frontmatter block, or with an unclosed one, is all body.
+fn split_frontmatter(text: &str) -> (Vec<(String, String)>, &str) {
+    let mut lines = text.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some("---") {
+        return (Vec::new(), text);
+    }
+    let mut offset = text.find('\n').map_or(text.len(), |end| end + 1);
+    let start = offset;
+    for line in lines {
+        let end = offset;
+        offset += line.len();
+        if matches!(line.trim_end(), "---" | "...") {
+            return (parse_frontmatter(&text[start..end]), &text[offset..]);
+        }
+    }
+    (Vec::new(), text)
+}
+
+/// Parse frontmatter lines into properties, in order
+///
+/// Keys are lowercased with spaces replaced by `-`, as in Logseq property
+/// names. Keys without a value are dropped. A `title` key is dropped too, as
+/// the title comes from the filename.
+fn parse_frontmatter(text: &str) -> Vec<(String, String)> {
+    let mut properties: Vec<(String, Vec<String>)> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some((_, values)) = properties.last_mut() {
+                values.push(unquote(item.trim()));
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase().replace(' ', "-");
+        let value = value.trim();
+        let values = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(list) => list
+                .split(',')
+                .map(|item| unquote(item.trim()))
+                .filter(|item| !item.is_empty())
+                .collect(),
+            None if value.is_empty() => Vec::new(),
+            None => vec![unquote(value)],
+        };
+        properties.push((key, values));
+    }
+    properties
+        .into_iter()
+        .filter(|(key, values)| !key.is_empty() && key != "title" && !values.is_empty())
+        .map(|(key, values)| (key, values.join(", ")))
+        .collect()
+}
+
+/// Strip the quotes of a quoted YAML scalar
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        // Double-quoted YAML escapes are close enough to JSON's
+        serde_json::from_str(value).unwrap_or_else(|_| value[1..value.len() - 1].to_string())
+    } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else {
+        value.to_string()
+    }
+}
+
+/// Convert a Markdown body into indented content blocks
+fn parse_blocks(body: &str) -> Vec<(String, u8)> {
+    let mut blocks: Vec<(String, u8)> = Vec::new();
+    // Levels of the enclosing headings
+    let mut headings: Vec<usize> = Vec::new();
+    // Columns of the markers of the enclosing list items
+    let mut lists: Vec<usize> = Vec::new();
+    // Paragraph or list item that following lines continue, up to the next
+    // blank line
+    let mut paragraph: Option<usize> = None;
+    // Block and marker of the open code fence, with its column
+    let mut fence: Option<(usize, &str, usize)> = None;
+
+    for line in body.lines() {
+        let text = line.trim_start();
+        let column = line.len() - text.len();
+        if let Some((block, marker, fence_column)) = fence {
+            let content = &mut blocks[block].0;
+            content.push('\n');
+            content.push_str(strip_indent(line, fence_column).trim_end());
+            if text.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if text.is_empty() {
+            paragraph = None;
+            continue;
+        }
+
+        if let Some(level) = heading_level(text) {
+            lists.clear();
+            while headings.last().is_some_and(|open| *open >= level) {
+                headings.pop();
+            }
+            blocks.push((text.trim_end().to_string(), depth(headings.len())));
+            headings.push(level);
+            paragraph = None;
+            continue;
+        }
+        if let Some(item) = list_item(text) {
+            while lists.last().is_some_and(|open| *open >= column) {
+                lists.pop();
+            }
+            blocks.push((
+                item.trim_end().to_string(),
+                depth(headings.len() + lists.len()),
+            ));
+            lists.push(column);
+            paragraph = None;
+            // Logseq writes a code block on the line of its bullet, with its
+            // other lines aligned to the text of the item
+            match fence_marker(item) {
+                Some(marker) => {
+                    fence = Some((blocks.len() - 1, marker, line.len() - item.len()));
+                }
+                None => paragraph = Some(blocks.len() - 1),
+            }
+            continue;
+        }
+        let fence_marker = fence_marker(text);
+        if let (Some(block), None) = (paragraph, fence_marker) {
+            let content = &mut blocks[block].0;
+            content.push(' ');
+            content.push_str(text.trim_end());
+            continue;
+        }
+
+        // Text outside the enclosing list items ends them
+        while lists.last().is_some_and(|open| *open >= column) {
+            lists.pop();
+        }
+        blocks.push((
+            text.trim_end().to_string(),
+            depth(headings.len() + lists.len()),
+        ));
+        paragraph = None;
+        match fence_marker {
+            Some(marker) => fence = Some((blocks.len() - 1, marker, column)),
+            None => paragraph = Some(blocks.len() - 1),
+        }
+    }
+    blocks
+}
+
+/// Get the marker of the code fence a line opens, if any
+fn fence_marker(text: &str) -> Option<&'static str> {
+    ["```", "~~~"]
+        .into_iter()
+        .find(|marker| text.starts_with(marker))
+}
+
+/// Get the level of an ATX heading line, e.g. 2 for `## Dosage`
+fn heading_level(text: &str) -> Option<usize> {
+    let level = text.chars().take_while(|c| *c == '#').count();
+    let rest = &text[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' '))).then_some(level)
+}
+
+/// Get the text of a list item line, without its `-`, `*`, `+` or `1.`
+/// marker
+fn list_item(text: &str) -> Option<&str> {
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| text.strip_prefix(marker))
+    {
+        return Some(item);
+    }
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    text[digits..]
+        .strip_prefix(". ")
+        .or_else(|| text[digits..].strip_prefix(") "))
+}
+
+/// Remove up to `width` leading spaces from a line
+fn strip_indent(line: &str, width: usize) -> &str {
+    let spaces = line.len() - line.trim_start_matches(' ').len();
+    &line[spaces.min(width)..]
+}
+
+/// Convert a nesting depth to an indentation level
+fn depth(depth: usize) -> u8 {
+    depth.min(u8::MAX as usize) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a content block
+    fn block(text: &str, indent: u8) -> (String, u8) {
+        (text.to_string(), indent)
+    }
+
+    #[test]
+    fn fence_may_open_on_a_list_item() {
+        let text = "- Example\n- ```rust\n  fn main() {\n      run();\n  }\n  ```\n- After\n";
+        assert_eq!(
+            parse_blocks(text),
+            [
+                block("Example", 0),
+                block("```rust\nfn main() {\n    run();\n}\n```", 0),
+                block("After", 0),
+            ]
+        );
+    }
+
+    /// Build a property
+    fn property(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn frontmatter_becomes_properties() {
+        let text = [
+            "---",
+            "title: Aspirin",
+            r#"Tags: [Drug, "NSAID", ]"#,
+            "Source Name: 'Max''s notes'",
+            "aliases:",
+            "  - ASA",
+            r#"  - "Acetylsalicylic acid""#,
+            "empty:",
+            "# comment",
+            r#"url: "https://example.org/a\u0021""#,
+            "---",
+            "Body",
+        ]
+        .join("\n");
+        let page = from_markdown("Aspirin", &text);
+        assert_eq!(
+            page.properties,
+            [
+                property("tags", "Drug, NSAID"),
+                property("source-name", "Max's notes"),
+                property("aliases", "ASA, Acetylsalicylic acid"),
+                property("url", "https://example.org/a!"),
+            ]
+        );
+        assert_eq!(page.contents, [block("Body", 0)]);
+    }
+
+    #[test]
+    fn frontmatter_may_end_with_dots() {
+        let (properties, body) = split_frontmatter("---\ntags: Drug\n...\n- Dosage\n");
+        assert_eq!(properties, [property("tags", "Drug")]);
+        assert_eq!(body, "- Dosage\n");
+    }
+
+    #[test]
+    fn unclosed_frontmatter_is_body() {
+        let text = "---\ntags: Drug\n\nBody\n";
+        let (properties, body) = split_frontmatter(text);
+        assert!(properties.is_empty());
+        assert_eq!(body, text);
+
+        let text = "Body\n---\ntags: Drug\n---\n";
+        assert!(split_frontmatter(text).0.is_empty());
+    }
+
+    #[test]
+    fn blocks_nest_under_headings_and_list_items() {
+        let text = [
+            "# Drug",
+            "Intro line",
+            "second line",
+            "## Dosage",
+            "- 500 mg",
+            "  - with food",
+            "    and water",
+            "- daily",
+            "1. first",
+            "2) second",
+            "# Other",
+            "#hashtag",
+        ]
+        .join("\n");
+        assert_eq!(
+            parse_blocks(&text),
+            [
+                block("# Drug", 0),
+                block("Intro line second line", 1),
+                block("## Dosage", 1),
+                block("500 mg", 2),
+                block("with food and water", 3),
+                block("daily", 2),
+                block("first", 2),
+                block("second", 2),
+                block("# Other", 0),
+                block("#hashtag", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_lines_end_paragraphs() {
+        assert_eq!(
+            parse_blocks("One\ntwo\n\nThree\n"),
+            [block("One two", 0), block("Three", 0)]
+        );
+    }
+
+    #[test]
+    fn fenced_code_keeps_its_lines() {
+        let text = "Text\n```\ncode\n\n- not an item\n```\n~~~\n```\n~~~\nAfter\n";
+        assert_eq!(
+            parse_blocks(text),
+            [
+                block("Text", 0),
+                block("```\ncode\n\n- not an item\n```", 0),
+                block("~~~\n```\n~~~", 0),
+                block("After", 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,367 @@
+/// Page link scanning and resolution
+///
+/// Links are written as `[[Title]]` and block references as `((uuid))`.
+/// Pages may declare alternative names in an `alias::` property
+/// (comma-separated), and links using an alias resolve to the page declaring
+/// it. Like Logseq, resolution ignores case.
+///
+/// Brackets that aren't links can be escaped with backslashes, as in
+/// `\[[not a link]]` or `\[\[not a link\]\]`, and are skipped by link
+/// scanning. A doubled backslash is a literal one, so `\\[[Title]]` is a link.
+use std::collections::{HashMap, HashSet};
+
+use crate::config::PropertyStyle;
+use crate::file_manager::LogseqPage;
+
+/// Extract the targets of all `[[...]]` links in a line
+pub fn extract_links(line: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut rest = line;
+    while let Some(start) = find_link_start(rest) {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else {
+            break;
+        };
+        let target = rest[..end].trim();
+        if !target.is_empty() {
+            links.push(target.to_string());
+        }
+        rest = &rest[end + 2..];
+    }
+    links
+}
+
+/// Find the first `[[` that isn't escaped with a backslash
+///
+/// Only an odd number of backslashes escapes the brackets, as an even number
+/// escapes the backslashes themselves.
+fn find_link_start(text: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("[[").map(|start| start + offset) {
+        let backslashes = text[..start].len() - text[..start].trim_end_matches('\\').len();
+        if backslashes.is_multiple_of(2) {
+            return Some(start);
+        }
+        offset = start + 2;
+    }
+    None
+}
+
+/// Replace the targets of links to `from` with `to`, ignoring case
+pub fn rename_links(line: &str, from: &str, to: &str) -> String {
+    let mut renamed = String::new();
+    let mut rest = line;
+    while let Some(start) = find_link_start(rest) {
+        let Some(end) = rest[start + 2..].find("]]").map(|end| end + start + 2) else {
+            break;
+        };
+        let target = &rest[start + 2..end];
+        renamed.push_str(&rest[..start + 2]);
+        if same_title(target.trim(), from) {
+            renamed.push_str(to);
+        } else {
+            renamed.push_str(target);
+        }
+        renamed.push_str("]]");
+        rest = &rest[end + 2..];
+    }
+    renamed.push_str(rest);
+    renamed
+}
+
+/// Replace `from` with `to` in a comma-separated property value, ignoring case
+///
+/// Names keep their surrounding `[[` and `]]`, if any.
+pub fn rename_in_page_list(value: &str, from: &str, to: &str) -> String {
+    value
+        .split(',')
+        .map(|name| {
+            let bare = name.trim();
+            let bare = bare
+                .strip_prefix("[[")
+                .and_then(|bare| bare.strip_suffix("]]"))
+                .unwrap_or(bare);
+            if same_title(bare.trim(), from) {
+                name.replacen(bare, to, 1)
+            } else {
+                name.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// A line of a page changed by renaming a linked page
+pub struct RenamedLine {
+    /// 1-based file line
+    pub line: usize,
+    /// Line before the rename
+    pub before: String,
+    /// Line after the rename
+    pub after: String,
+    /// Number of links and tags renamed on the line
+    pub references: usize,
+}
+
+/// Rename the links to a page within another page
+///
+/// Returns the updated page and the lines that changed, with property lines
+/// shown as written in `style`.
+pub fn rename_links_in_page(
+    page: &LogseqPage,
+    from: &str,
+    to: &str,
+    style: PropertyStyle,
+) -> (LogseqPage, Vec<RenamedLine>) {
+    let mut changed = Vec::new();
+    let properties = page
+        .properties
+        .iter()
+        .enumerate()
+        .map(|(i, (key, value))| {
+            let (renamed, references) = if key == "tags" {
+                (
+                    rename_in_page_list(value, from, to),
+                    count_in_page_list(value, from),
+                )
+            } else {
+                (rename_links(value, from, to), count_links(value, from))
+            };
+            if renamed != *value {
+                changed.push(RenamedLine {
+                    line: i + 1,
+                    before: format!("{}{}{}", key, style.separator(), value),
+                    after: format!("{}{}{}", key, style.separator(), renamed),
+                    references,
+                });
+            }
+            (key.clone(), renamed)
+        })
+        .collect();
+    let contents = page
+        .contents
+        .iter()
+        .enumerate()
+        .map(|(i, (line, indent))| {
+            let renamed = rename_links(line, from, to);
+            if renamed != *line {
+                changed.push(RenamedLine {
+                    line: page.line_number(i),
+                    before: line.clone(),
+                    after: renamed.clone(),
+                    references: count_links(line, from),
+                });
+            }
+            (renamed, *indent)
+        })
+        .collect();
+    (LogseqPage::new(&page.title, properties, contents), changed)
+}
+
+/// Count the links to `from` in a line, ignoring case
+fn count_links(line: &str, from: &str) -> usize {
+    extract_links(line)
+        .iter()
+        .filter(|target| same_title(target, from))
+        .count()
+}
+
+/// Count the names equal to `from` in a comma-separated property value,
+/// ignoring case
+fn count_in_page_list(value: &str, from: &str) -> usize {
+    value
+        .split(',')
+        .map(|name| {
+            let bare = name.trim();
+            bare.strip_prefix("[[")
+                .and_then(|bare| bare.strip_suffix("]]"))
+                .unwrap_or(bare)
+        })
+        .filter(|bare| same_title(bare.trim(), from))
+        .count()
+}
+
+/// Check if two page names refer to the same page, ignoring case
+fn same_title(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Extract the UUIDs of all `((uuid))` block references in a line
+///
+/// Double parentheses around anything but a UUID, as in `f((x))`, are
+/// ordinary text.
+pub fn extract_block_refs(line: &str) -> Vec<String> {
+    let mut refs = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("((") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("))") else {
+            break;
+        };
+        let uuid = rest[..end].trim();
+        if is_uuid(uuid) {
+            refs.push(uuid.to_string());
+            rest = &rest[end + 2..];
+        }
+    }
+    refs
+}
+
+/// Check if text is a UUID in its hyphenated form, e.g.
+/// `6500a1b2-3c4d-4e5f-8a9b-0c1d2e3f4a5b`
+fn is_uuid(text: &str) -> bool {
+    let groups = text.split('-').collect::<Vec<_>>();
+    groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12])
+        && groups
+            .iter()
+            .all(|group| group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Parse a comma-separated property value into page names
+///
+/// Surrounding `[[` and `]]` are stripped from each name.
+pub fn parse_page_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|name| {
+            let name = name.trim();
+            name.strip_prefix("[[")
+                .and_then(|name| name.strip_suffix("]]"))
+                .unwrap_or(name)
+                .trim()
+                .to_string()
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Map from page titles and aliases to canonical page titles
+pub struct AliasMap {
+    names: HashMap<String, String>,
+}
+
+impl AliasMap {
+    /// Build the map from all pages of the vault
+    ///
+    /// Titles take precedence over aliases of other pages.
+    pub fn new(pages: &[LogseqPage]) -> Self {
+        let mut names = HashMap::new();
+        for page in pages {
+            if let Some(aliases) = page.property("alias") {
+                for alias in parse_page_list(aliases) {
+                    names.insert(alias.to_lowercase(), page.title.clone());
+                }
+            }
+        }
+        for page in pages {
+            names.insert(page.title.to_lowercase(), page.title.clone());
+        }
+        Self { names }
+    }
+
+    /// Resolve a link target to the canonical title of an existing page
+    pub fn resolve(&self, target: &str) -> Option<&str> {
+        self.names.get(&target.to_lowercase()).map(String::as_str)
+    }
+
+    /// Get the canonical titles of the existing pages a page links to
+    ///
+    /// Links in the page's contents are resolved through aliases. Broken
+    /// links are left out.
+    pub fn linked_pages(&self, page: &LogseqPage) -> HashSet<&str> {
+        page.contents
+            .iter()
+            .flat_map(|(line, _)| extract_links(line))
+            .filter_map(|target| self.resolve(&target))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_brackets_are_not_links() {
+        assert!(extract_links(r"\[[not a link]]").is_empty());
+        assert!(extract_links(r"\[\[not a link\]\]").is_empty());
+        assert_eq!(
+            rename_links(r"\[[Aspirin]]", "Aspirin", "Acetylsalicylic acid"),
+            r"\[[Aspirin]]"
+        );
+    }
+
+    #[test]
+    fn doubled_backslash_doesnt_escape_a_link() {
+        assert_eq!(extract_links(r"\\[[Aspirin]]"), vec!["Aspirin"]);
+        assert!(extract_links(r"\\\[[Aspirin]]").is_empty());
+    }
+
+    #[test]
+    fn escaped_and_unescaped_links_on_one_line() {
+        let line = r"a[\[[0]]] is not [[Aspirin]], nor \[\[x\]\] or \[[y]], but [[Metformin]] is";
+        assert_eq!(extract_links(line), vec!["Aspirin", "Metformin"]);
+        assert_eq!(
+            rename_links(line, "aspirin", "Acetylsalicylic acid"),
+            r"a[\[[0]]] is not [[Acetylsalicylic acid]], nor \[\[x\]\] or \[[y]], but [[Metformin]] is"
+        );
+    }
+
+    #[test]
+    fn block_refs_must_hold_a_uuid() {
+        let line = "See ((6500a1b2-3c4d-4e5f-8a9b-0C1D2E3F4A5B)) for f((x)) and ((not-a-uuid))";
+        assert_eq!(
+            extract_block_refs(line),
+            ["6500a1b2-3c4d-4e5f-8a9b-0C1D2E3F4A5B"]
+        );
+    }
+
+    #[test]
+    fn block_refs_are_found_after_other_parentheses() {
+        let line = "((a) ((6500a1b2-3c4d-4e5f-8a9b-0c1d2e3f4a5b))";
+        assert_eq!(
+            extract_block_refs(line),
+            ["6500a1b2-3c4d-4e5f-8a9b-0c1d2e3f4a5b"]
+        );
+        assert!(extract_block_refs("g((y(z)))").is_empty());
+    }
+
+    #[test]
+    fn renamed_lines_match_the_written_page_in_both_styles() {
+        let page = LogseqPage::new(
+            "Note",
+            vec![
+                ("tags".into(), "[[Drug/ASA]], Review".into()),
+                ("source".into(), "See [[drug/asa]]".into()),
+            ],
+            vec![
+                ("Take [[Drug/ASA]] daily".into(), 0),
+                ("Unrelated".into(), 0),
+            ],
+        );
+        for style in [PropertyStyle::Spaced, PropertyStyle::Compact] {
+            let (renamed, changed) = rename_links_in_page(&page, "Drug/ASA", "Drug/Aspirin", style);
+            let before = page.to_text(style);
+            let after = renamed.to_text(style);
+            let before = before.lines().collect::<Vec<_>>();
+            let after = after.lines().collect::<Vec<_>>();
+
+            assert_eq!(changed.len(), 3);
+            // Property lines are shown whole, content lines without their bullet
+            for line in &changed {
+                let bullet = if line.line > page.properties.len() {
+                    "- "
+                } else {
+                    ""
+                };
+                assert_eq!(before[line.line - 1], format!("{}{}", bullet, line.before));
+                assert_eq!(after[line.line - 1], format!("{}{}", bullet, line.after));
+            }
+            let lines = after
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(LogseqPage::parse("Note", &lines), renamed);
+        }
+    }
+}
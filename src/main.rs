@@ -2,21 +2,59 @@
 ///
 /// This module implements the command-line interface for managing SVLMD,
 /// including initialization, synchronization, and version control features.
+mod changelog;
+mod config;
+mod doctor;
+mod export;
 mod file_manager;
+mod ignore;
+mod import;
+mod links;
+mod progress;
+mod rename_map;
+mod table;
+mod template;
+mod validate;
+mod version;
+mod watch;
+mod writer;
 
 use anyhow::{bail, Context, Ok, Result};
-use chrono::Utc;
-use clap::{Parser, Subcommand};
-use dialoguer::Input;
-use std::fs::{self, OpenOptions};
-use std::path::Path;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use dialoguer::{Confirm, Editor, Input};
+use serde::Serialize;
+use similar::TextDiff;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use crate::file_manager::{FileManager, LogseqPage};
+use crate::config::{ChangelogHeadings, ChangelogLinks, Config, VersionSource};
+use crate::file_manager::{ChangeOptions, ChangedPages, FileManager, LogseqPage};
+use crate::links::AliasMap;
+use crate::table::Table;
+use crate::validate::DEFAULT_MAX_DEPTH;
+use crate::writer::DryRun;
 
 /// CLI configuration and command parsing structure
 #[derive(Parser)]
 #[command(name = "svlmd")]
 struct Cli {
+    /// Project root, overriding detection from the executable location
+    #[arg(long, global = true)]
+    root: Option<PathBuf>,
+    /// Modify pages in bulk without asking for confirmation
+    #[arg(long, short = 'y', global = true)]
+    yes: bool,
+    /// Print the files that would be written and the git operations that
+    /// would run, without making any change
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Never color the output
+    #[arg(long, global = true)]
+    no_color: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -25,284 +63,1338 @@ struct Cli {
 #[derive(Subcommand, PartialEq)]
 enum Commands {
     /// Initialize SVLMD with contributor information
-    Init,
+    Init(InitArgs),
+    /// Repair a partially initialized vault, keeping what's valid
+    Reinit,
+    /// Check that svlmd works here by running init, new and sync on a
+    /// throwaway vault in a temporary directory
+    SelfTest,
     /// Sync database
-    Sync {
-        /// Sync the version metadata
-        #[arg(long, short = 'V')]
-        version: bool,
-        /// Verbose output mode
-        #[arg(long, short = 'v')]
-        verbose: bool,
+    Sync(SyncArgs),
+    /// Sync, commit, and tag a release of the current version
+    Release(ReleaseArgs),
+    /// Show staged page changes
+    Status(StatusArgs),
+    /// Rebuild all version pages from git tags
+    Rebuild,
+    /// Watch pages and show the status whenever they change
+    Watch {
+        /// Sync the version instead of showing the status
+        #[arg(long)]
+        sync: bool,
+        /// Milliseconds without changes before a burst of changes is handled
+        #[arg(long, value_name = "MS", default_value_t = watch::DEFAULT_DEBOUNCE.as_millis() as u64)]
+        debounce: u64,
     },
+    /// Create a new page
+    New {
+        /// Title of the page to create
+        title: String,
+        /// Template to instantiate from templates/
+        #[arg(long, short = 't')]
+        template: Option<String>,
+    },
+    /// List pages linking to a page
+    Backlinks {
+        /// Title or alias of the linked page
+        title: String,
+    },
+    /// Rank pages by the number of pages linking to them
+    Hubs {
+        /// Number of pages to show
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+        /// Print the pages as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// Rename a page and update the links to it
+    Rename {
+        /// Title of the page to rename
+        from: String,
+        /// New title of the page
+        to: String,
+    },
+    /// Import plain Markdown files as pages
+    Import {
+        /// Directory of the Markdown files to import
+        dir: PathBuf,
+        /// Replace existing pages with the same title instead of skipping them
+        #[arg(long)]
+        overwrite: bool,
+    },
+    /// Rename several pages listed in a CSV or JSON rename map
+    RenameMap {
+        /// Rename map file, with `old,new` lines or, ending in .json, a JSON
+        /// object of old and new titles
+        file: PathBuf,
+    },
+    /// Add or remove an alias of a page
+    Alias {
+        /// Title of the page
+        page: String,
+        /// Whether to add or remove the alias
+        #[arg(value_enum)]
+        action: AliasAction,
+        /// The alias
+        value: String,
+    },
+    /// Add a tag to every page matching the given filters
+    #[command(group = clap::ArgGroup::new("filter").required(true).multiple(true))]
+    Retag {
+        /// The tag to add
+        tag: String,
+        /// Match pages whose title matches this glob (e.g. `Drug/*`)
+        #[arg(long, group = "filter")]
+        title_glob: Option<String>,
+        /// Match pages whose contents contain this text, ignoring case
+        #[arg(long, group = "filter")]
+        content_contains: Option<String>,
+    },
+    /// Show page, block, link and tag counts of the vault
+    Stats {
+        /// Layout of the statistics
+        #[arg(long, value_enum, default_value_t = OutputFormat::Plain, conflicts_with = "json")]
+        format: OutputFormat,
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check the health of the vault
+    Doctor {
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+        /// Recreate missing author pages before checking
+        #[arg(long)]
+        fix: bool,
+    },
+    /// List all pages
+    List(ListArgs),
+    /// Search page titles and contents, ignoring case
+    Search {
+        /// Text to search for
+        query: String,
+        #[command(flatten)]
+        pagination: PaginationArgs,
+        /// Print the matches as JSON
+        #[arg(long)]
+        json: bool,
+        /// Print each match as a JSON object on its own line as it's found
+        #[arg(long, conflicts_with = "json")]
+        json_lines: bool,
+    },
+    /// Remove duplicate pages from version page changelogs
+    DedupeChangelog {
+        /// Title of the version page to clean; all version pages if omitted
+        title: Option<String>,
+    },
+    /// List pages never recorded as added in any version page
+    Unrecorded {
+        /// Order of the listed pages
+        #[arg(long, value_enum, default_value_t = PageOrder::Title)]
+        sort: PageOrder,
+        /// Print the pages as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// List pages with unstaged edits, which a commit would leave out
+    Unstaged {
+        /// Exit with an error if any page has unstaged edits
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Validate pages against the style guide
+    Validate {
+        /// Maximum allowed block nesting depth
+        #[arg(long, default_value_t = DEFAULT_MAX_DEPTH)]
+        max_depth: u8,
+        /// Hide the progress bar
+        #[arg(long, short = 'q')]
+        quiet: bool,
+        /// Fail if any issue is found, for use as a CI gate
+        #[arg(long)]
+        check_only: bool,
+    },
+    /// Export pages to a directory
+    Export {
+        /// Page to print instead of exporting all pages
+        #[arg(conflicts_with_all = ["output", "public_only"])]
+        title: Option<String>,
+        /// Directory to write the exported pages to
+        #[arg(long, short = 'o', required_unless_present = "title")]
+        output: Option<PathBuf>,
+        /// Export only pages marked `public:: true`
+        #[arg(long)]
+        public_only: bool,
+        /// Format of the exported pages
+        #[arg(long, value_enum, default_value_t = ExportFormat::Logseq)]
+        format: ExportFormat,
+        /// Leave out the page properties, keeping only the content blocks
+        #[arg(long)]
+        strip_properties: bool,
+    },
+    /// Check that rewriting each page leaves its file unchanged
+    CheckRoundtrip {
+        /// Hide the progress bar
+        #[arg(long, short = 'q')]
+        quiet: bool,
+    },
+    /// Reformat all pages
+    Fmt {
+        /// Maximum allowed block nesting depth
+        #[arg(long, default_value_t = DEFAULT_MAX_DEPTH)]
+        max_depth: u8,
+        /// Outdent blocks indented more than one level below their parent
+        #[arg(long)]
+        fix_indent: bool,
+        /// Collapse runs of blank lines and empty blocks into one
+        #[arg(long)]
+        compact_blanks: bool,
+        /// Remove empty change sections and entries from version pages
+        #[arg(long)]
+        prune: bool,
+        /// Hide the progress bar
+        #[arg(long, short = 'q')]
+        quiet: bool,
+    },
+}
+
+/// Change made by the alias command
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum AliasAction {
+    Add,
+    Remove,
+}
+
+/// Format of exported pages
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ExportFormat {
+    /// Logseq Markdown, as written to page files
+    Logseq,
+    /// The parsed page structure as JSON
+    Json,
+    /// CommonMark, with the title and properties as YAML frontmatter
+    Markdown,
+    /// Org-mode headlines, with the title and properties as `#+TITLE:` and
+    /// `#+PROPERTY:` lines
+    Org,
+}
+
+impl ExportFormat {
+    /// Extension of files exported in this format
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Logseq | Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Org => "org",
+        }
+    }
+}
+
+/// Layout of human-readable output
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// One item per line
+    Plain,
+    /// Aligned columns with a header row
+    Table,
+}
+
+/// Field of a page printed by `list --fields`
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ListField {
+    /// Title of the page
+    Title,
+    /// Tags of the page
+    Tags,
+    /// The `created-date::` property of the page
+    CreatedDate,
+    /// Modification date of the page file
+    LastModified,
+}
+
+impl ListField {
+    /// Fields printed in table format when `--fields` isn't given
+    const TABLE_DEFAULT: [ListField; 3] = [Self::Title, Self::Tags, Self::LastModified];
+
+    /// Name of the field, as given to `--fields` and used as a JSON key
+    fn name(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Tags => "tags",
+            Self::CreatedDate => "created-date",
+            Self::LastModified => "last-modified",
+        }
+    }
+
+    /// Header of the field's table column
+    fn header(self) -> &'static str {
+        match self {
+            Self::Title => "Title",
+            Self::Tags => "Tags",
+            Self::CreatedDate => "Created",
+            Self::LastModified => "Last Modified",
+        }
+    }
+}
+
+/// Kind of change of a changed page
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    Conflicted,
+}
+
+/// Grouping of listed changed pages
+#[derive(Clone, Copy, PartialEq, Default, ValueEnum)]
+enum GroupBy {
+    /// By kind of change
+    #[default]
+    Change,
+    /// By the author of the last commit touching each page
+    Author,
+}
+
+/// Order in which pages are listed
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum PageOrder {
+    /// Alphabetically by title
+    Title,
+    /// Most recently modified first
+    Modified,
+}
+
+/// Arguments selecting a page of sorted results
+#[derive(Args, PartialEq)]
+struct PaginationArgs {
+    /// Maximum number of results to show
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Number of results to skip
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
+}
+
+impl PaginationArgs {
+    /// Select the requested page of results
+    fn apply<T>(&self, items: Vec<T>) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Wrap a page of results in a JSON envelope with pagination metadata
+    fn envelope<T: Serialize>(&self, total: usize, items: &[T]) -> serde_json::Value {
+        serde_json::json!({
+            "total": total,
+            "offset": self.offset,
+            "limit": self.limit,
+            "items": items,
+        })
+    }
+}
+
+/// Stream of results printed as JSON lines
+///
+/// Each result is printed as soon as it's found, one object per line, so
+/// consumers can process results before the query finishes. Results outside
+/// the requested page are skipped.
+struct JsonLines {
+    skip: usize,
+    remaining: usize,
+}
+
+impl JsonLines {
+    /// Start a stream of the page of results selected by `pagination`
+    fn new(pagination: &PaginationArgs) -> Self {
+        Self {
+            skip: pagination.offset,
+            remaining: pagination.limit.unwrap_or(usize::MAX),
+        }
+    }
+
+    /// Print a result, unless it falls before the requested page
+    ///
+    /// Returns false once the page is full or stdout was closed, such as by
+    /// a pager, after which no further results are wanted.
+    fn emit<T: Serialize>(&mut self, item: &T) -> Result<bool> {
+        if self.remaining == 0 {
+            return Ok(false);
+        }
+        if self.skip > 0 {
+            self.skip -= 1;
+            return Ok(true);
+        }
+        let line = serde_json::to_string(item)?;
+        match writeln!(io::stdout(), "{}", line) {
+            std::result::Result::Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(false),
+            Err(e) => return Err(e).context("Failed to write to stdout"),
+        }
+        self.remaining -= 1;
+        Ok(self.remaining > 0)
+    }
+}
+
+/// Number of pages under a collapsed namespace, as printed by `list --depth`
+#[derive(Serialize)]
+struct NamespaceCount {
+    title: String,
+    pages: usize,
+}
+
+/// Statistics of the vault, as printed by the stats command
+#[derive(Serialize)]
+struct Stats {
+    /// Number of pages
+    pages: usize,
+    /// Number of content blocks, without blank lines and empty bullets
+    blocks: usize,
+    /// Number of page links
+    links: usize,
+    /// Number of pages without tags
+    untagged: usize,
+    /// Number of pages carrying each tag, most frequent first
+    tags: Vec<TagCount>,
+}
+
+/// Number of pages carrying a tag
+#[derive(Serialize)]
+struct TagCount {
+    tag: String,
+    count: usize,
+}
+
+/// Path of the manifest written by `sync --manifest`, relative to the project
+/// root
+const SYNC_MANIFEST: &str = ".svlmd-sync.json";
+
+/// Record of the last sync, as written by `sync --manifest`
+#[derive(Serialize)]
+struct SyncManifest<'a> {
+    /// Version the changes were recorded under
+    version: String,
+    /// Time of the sync, in RFC 3339 format
+    synced_at: String,
+    /// Titles of the pages recorded as added
+    added: &'a [String],
+    /// Titles of the pages recorded as modified
+    modified: &'a [String],
+    /// Titles of the pages recorded as deleted
+    deleted: &'a [String],
+}
+
+/// A page and the number of pages linking to it, as printed by `hubs`
+#[derive(Serialize)]
+struct Hub {
+    title: String,
+    links: usize,
+}
+
+/// A line of a page matching a search
+#[derive(Serialize)]
+struct SearchMatch {
+    /// Title of the matching page
+    title: String,
+    /// 1-based line of the match, or `None` if the title matched
+    line: Option<usize>,
+    /// Text of the matching line
+    text: String,
+}
+
+/// Arguments of the sync command
+#[derive(Args, PartialEq, Default)]
+struct SyncArgs {
+    /// Sync the version metadata
+    #[arg(long, short = 'V')]
+    version: bool,
+    /// Verbose output mode
+    #[arg(long, short = 'v')]
+    verbose: bool,
+    /// Commit the staged changes together with the updated version pages
+    #[arg(long, short = 'c')]
+    commit: bool,
+    /// Rebuild the current version's entry from the current changes only
+    #[arg(long)]
+    force_recreate: bool,
+    /// Record the changes under this version instead of the current one
+    ///
+    /// Named `--target-version` because `--version` already syncs the
+    /// version metadata.
+    #[arg(long, value_name = "VER", value_parser = semver::Version::parse)]
+    target_version: Option<semver::Version>,
+    /// Print only the number of changed pages instead of listing them
+    #[arg(long)]
+    count_only: bool,
+    /// Edit the Summary of the version page in $EDITOR
+    #[arg(long)]
+    edit: bool,
+    /// Seed the Summary of a new version page with the subjects of the
+    /// commits since the previous version tag
+    #[arg(long)]
+    summary_from_commits: bool,
+    /// Print the changelog entry the sync would record, without writing
+    #[arg(long, conflicts_with_all = ["commit", "edit"])]
+    report_only: bool,
+    /// Refuse to sync if any page has merge conflicts
+    #[arg(long)]
+    strict: bool,
+    /// Write the synced version and changes to .svlmd-sync.json
+    #[arg(long, conflicts_with = "report_only")]
+    manifest: bool,
+    /// Remove empty change sections and entries from the version page
+    #[arg(long, conflicts_with = "report_only")]
+    prune: bool,
+    /// Report only the pages last changed by this author
+    #[arg(long, value_name = "NAME", requires = "report_only")]
+    author: Option<String>,
+    /// How to group the changed pages printed in verbose mode
+    #[arg(long, value_enum, default_value_t, conflicts_with = "count_only")]
+    group_by: GroupBy,
+    #[command(flatten)]
+    changes: ChangeArgs,
+}
+
+/// Arguments of the init command
+#[derive(Args, PartialEq)]
+struct InitArgs {
+    /// Create a git repository with an initial commit if the root isn't one
+    #[arg(long)]
+    init_git: bool,
+    /// Write the version file with this version if it's missing
+    #[arg(
+        long,
+        value_name = "VER",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_SEED_VERSION,
+        value_parser = semver::Version::parse,
+    )]
+    seed_version: Option<semver::Version>,
+    /// Overwrite an existing version file with the seed version
+    #[arg(long, requires = "seed_version")]
+    force: bool,
+}
+
+/// Version written to the version file of a new vault
+const DEFAULT_SEED_VERSION: &str = "0.1.0";
+
+/// Arguments of the release command
+#[derive(Args, PartialEq)]
+struct ReleaseArgs {
+    /// Release even with uncommitted changes outside the pages directory
+    #[arg(long)]
+    allow_dirty: bool,
+    /// Refuse to release if any page has unstaged edits
+    #[arg(long)]
+    strict: bool,
+    /// Verbose output mode
+    #[arg(long, short = 'v')]
+    verbose: bool,
+}
+
+/// Arguments of the list command
+#[derive(Args, PartialEq)]
+struct ListArgs {
+    #[command(flatten)]
+    pagination: PaginationArgs,
+    /// Collapse titles beyond N namespace levels, with the number of
+    /// pages under each
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..))]
+    depth: Option<u8>,
+    /// List only the pages recorded as added in this version or later
+    #[arg(long, value_name = "VER", value_parser = semver::Version::parse)]
+    since_version: Option<semver::Version>,
+    /// Layout of the listed pages
+    #[arg(long, value_enum, default_value_t = OutputFormat::Plain, conflicts_with_all = ["json", "json_lines"])]
+    format: OutputFormat,
+    /// Print the pages as JSON
+    #[arg(long)]
+    json: bool,
+    /// Print one JSON object per page, one per line
+    #[arg(long, conflicts_with = "json")]
+    json_lines: bool,
+    /// Comma-separated fields to print for each page, in order
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "FIELDS",
+        conflicts_with = "depth"
+    )]
+    fields: Vec<ListField>,
+}
+
+/// Arguments of the status command
+#[derive(Args, PartialEq, Default)]
+struct StatusArgs {
+    /// Print only the number of changed pages instead of listing them
+    #[arg(long)]
+    count_only: bool,
+    /// Print one `A`, `M` or `D` line per changed page, for scripts
+    #[arg(long, conflicts_with = "count_only")]
+    porcelain: bool,
+    /// How to group the changed pages
+    #[arg(long, value_enum, default_value_t, conflicts_with_all = ["count_only", "porcelain"])]
+    group_by: GroupBy,
+    /// Show only these kinds of change (repeatable or comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KIND")]
+    only: Vec<ChangeKind>,
+    /// Show only the pages last changed by this author
+    #[arg(long, value_name = "NAME")]
+    author: Option<String>,
+    /// Print the changed pages as a JSON object of lists by kind of change
+    #[arg(long, conflicts_with_all = ["count_only", "porcelain"])]
+    json: bool,
+    #[command(flatten)]
+    changes: ChangeArgs,
+}
+
+/// Arguments controlling which changed pages are detected
+#[derive(Args, PartialEq, Default)]
+struct ChangeArgs {
+    /// Ignore pages that aren't tracked in the last commit yet
+    #[arg(long)]
+    no_untracked: bool,
+    /// Include modified pages that only changed formatting or property order
+    #[arg(long)]
+    include_formatting: bool,
+    /// Print the raw git status flags of each page file to stderr
+    #[arg(long, hide = true)]
+    debug_status: bool,
+}
+
+impl ChangeArgs {
+    /// Convert the arguments to change detection options
+    fn options(&self) -> ChangeOptions {
+        ChangeOptions {
+            include_untracked: !self.no_untracked,
+            include_formatting: self.include_formatting,
+        }
+    }
+
+    /// Print the raw git statuses of the page files if `--debug-status` is set
+    fn print_debug_status(&self, file_manager: &FileManager) -> Result<()> {
+        if !self.debug_status {
+            return Ok(());
+        }
+        for (path, status) in file_manager.raw_page_statuses(&self.options())? {
+            eprintln!("{}: {}", path, file_manager::describe_status(status));
+        }
+        Ok(())
+    }
 }
 
 /// Initialize SVLMD configuration
 ///
 /// Creates or overwrites the .svlmd configuration file with contributor information.
-/// Prompts the user for their name and stores it in the configuration.
+/// Prompts the user for their name and email and stores them in the configuration,
+/// keeping any other settings from an existing configuration.
 fn init_config(root: &Path) -> Result<()> {
     if root.join(".svlmd").exists() {
         println!(".svlmd already exists. Overwriting...");
     }
 
-    let contributor_name: String = Input::new()
+    let config_path = root.join(".svlmd");
+    let mut config = existing_config(&config_path)?;
+
+    let contributor: String = Input::new()
         .with_prompt("Enter your name")
+        .validate_with(|name: &String| match contributor_name(name) {
+            Some(_) => std::result::Result::Ok(()),
+            None => Err("Name cannot be empty"),
+        })
         .interact_text()
         .context("Failed to get contributor name")?;
+    config.contributor = contributor_name(&contributor).unwrap_or_default();
 
-    let config = serde_json::json!({
-        "contributor": contributor_name
-    });
-
-    let file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(root.join(".svlmd"))
-        .context("Failed to create .svlmd")?;
+    // Default the email from git config
+    let mut email_input = Input::<String>::new()
+        .with_prompt("Enter your email (optional)")
+        .allow_empty(true);
+    if let Some(email) = config.email.clone().or_else(|| {
+        git2::Config::open_default()
+            .and_then(|git_config| git_config.get_string("user.email"))
+            .ok()
+    }) {
+        email_input = email_input.default(email);
+    }
+    let email = email_input
+        .interact_text()
+        .context("Failed to get contributor email")?;
+    config.email = Some(email.trim().to_string()).filter(|email| !email.is_empty());
 
-    serde_json::to_writer_pretty(&file, &config).context("Failed to write to .svlmd")?;
+    config.save(&config_path)?;
     println!("Initialized config.");
 
     Ok(())
 }
 
+/// Load the configuration that `init` updates
+///
+/// A missing or empty file gives the default configuration. A file that
+/// can't be parsed is an error rather than being replaced, as its settings
+/// would be lost.
+fn existing_config(path: &Path) -> Result<Config> {
+    match fs::read_to_string(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        std::result::Result::Ok(text) if text.trim().is_empty() => Ok(Config::default()),
+        std::result::Result::Ok(_) => {
+            Config::load(path).context("Fix or remove .svlmd, then run init again")
+        }
+    }
+}
+
+/// Normalize a contributor name as entered, or `None` if it is empty
+///
+/// The name becomes the author page title, so stray whitespace would break
+/// links to it.
+fn contributor_name(input: &str) -> Option<String> {
+    Some(input.trim().to_string()).filter(|name| !name.is_empty())
+}
+
 /// Initialize SVLMD system
 ///
 /// Sets up the SVLMD environment by:
 /// 1. Creating configuration if it doesn't exist
 /// 2. Initializing the file manager
 /// 3. Creating contributor's Logseq page if it doesn't exist
-fn init(root: &Path) -> Result<FileManager> {
+///
+/// With `dry_run`, the file manager only prints the changes it would make.
+fn init(root: &Path, dry_run: bool) -> Result<FileManager> {
     // Initialize the tool if not already initialized
     if !root.join(".svlmd").exists() {
+        if dry_run {
+            bail!("Config not found, run svlmd init first");
+        }
         println!("Config not found. Creating...");
         init_config(root)?;
         println!();
     }
 
-    let file_manager = FileManager::new()?;
+    let mut file_manager = FileManager::new(Some(root))?;
+    if dry_run {
+        file_manager = file_manager.with_writer(Arc::new(DryRun::new(root)));
+    }
 
-    if !file_manager.logseq_page_exists(&file_manager.contributor_name) {
-        file_manager.write_logseq_page(&LogseqPage::new(
-            &file_manager.contributor_name,
-            vec![
-                ("icon".into(), "🙂".into()),
-                ("exclude-from-graph-view".into(), "true".into()),
-                ("tags".into(), "Author".into()),
-            ],
-            vec![],
-        ))?;
+    if !file_manager.logseq_page_exists(file_manager.contributor()) {
+        file_manager.write_logseq_page(&file_manager.author_page(file_manager.contributor()))?;
     }
 
     Ok(file_manager)
 }
 
-/// Synchronize version information
+/// Handle the reinit command
 ///
-/// Updates version tracking by:
-/// 1. Reading the current version from version.txt
-/// 2. Creating or updating the version page in Logseq
-/// 3. Tracking changed pages since the last version
-fn sync_version(file_manager: &FileManager, verbose: bool) -> Result<()> {
-    let version_path = file_manager.root.join("version.txt");
-    if !version_path.exists() {
-        bail!("version.txt not found");
+/// Creates whatever an interrupted `init` left missing: the configuration
+/// (also when empty or without a contributor), the pages directory, the
+/// author page, and the text version file. Each repair is reported, and
+/// running it again on a complete vault changes nothing.
+fn reinit_command(root: &Path) -> Result<()> {
+    let config_path = root.join(".svlmd");
+    let mut repairs = 0;
+    let config_problem = match fs::read_to_string(&config_path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Some("is missing"),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", config_path.display()))
+        }
+        std::result::Result::Ok(text) if text.trim().is_empty() => Some("is empty"),
+        std::result::Result::Ok(_) => match FileManager::new(Some(root)) {
+            std::result::Result::Ok(_) => None,
+            Err(_)
+                if Config::load(&config_path)
+                    .is_ok_and(|config| config.contributor.trim().is_empty()) =>
+            {
+                Some("has no contributor")
+            }
+            // Anything else would be lost by recreating the file
+            Err(e) => return Err(e.context("Fix or remove .svlmd, then run reinit again")),
+        },
+    };
+    if let Some(problem) = config_problem {
+        println!(".svlmd {}, creating it", problem);
+        init_config(root)?;
+        repairs += 1;
     }
 
-    let version = semver::Version::parse(fs::read_to_string(version_path)?.lines().next().unwrap())
-        .context("Failed to parse version")?;
+    let file_manager = FileManager::new(Some(root))?;
+    let pages_dir = file_manager.pages_dir();
+    if !pages_dir.is_dir() {
+        fs::create_dir_all(&pages_dir)
+            .with_context(|| format!("Failed to create {}", pages_dir.display()))?;
+        println!("Created {}", pages_dir.display());
+        repairs += 1;
+    }
+    let contributor = file_manager.contributor();
+    if !file_manager.logseq_page_exists(contributor) {
+        file_manager.write_logseq_page(&file_manager.author_page(contributor))?;
+        println!("Created author page {}", contributor);
+        repairs += 1;
+    }
+    if let VersionSource::Text { path } = file_manager.version_source() {
+        if !file_manager.resolve(&path)?.exists() {
+            let version = semver::Version::parse(DEFAULT_SEED_VERSION)?;
+            seed_version(&file_manager, &version, false)?;
+            repairs += 1;
+        }
+    }
 
-    if verbose {
-        println!("Found version: {}", version);
+    match repairs {
+        0 => println!("Nothing to repair"),
+        repairs => println!("Made {} repairs", repairs),
+    }
+    Ok(())
+}
+
+/// Write the version to the text version file
+///
+/// An existing file is kept unless `force` is set.
+fn seed_version(file_manager: &FileManager, version: &semver::Version, force: bool) -> Result<()> {
+    let VersionSource::Text { path } = file_manager.version_source() else {
+        bail!("Can only seed the version of a text version source");
+    };
+    if file_manager.resolve(&path)?.exists() && !force {
+        println!("{} already exists. Skipping...", path);
+        return Ok(());
     }
+    file_manager.write(&path, format!("{}\n", version))?;
+    println!("Wrote {} with version {}", path, version);
+    Ok(())
+}
 
-    let version_page = format!("{}.{}.{}", version.major, version.minor, version.patch);
-    let changed_pages = file_manager.get_changed_pages()?;
+/// Bootstrap a git repository for a new vault
+///
+/// Creates the repository and commits the config, the version file, and the
+/// author page. A missing text version file is seeded with the default
+/// version. Does nothing if the root is already a repository.
+fn init_git(file_manager: &FileManager) -> Result<()> {
+    if !file_manager.init_repository()? {
+        println!("Git repository already exists. Skipping...");
+        return Ok(());
+    }
 
-    if verbose {
-        changed_pages[0]
-            .iter()
-            .for_each(|page| println!("+ {}", page));
-        changed_pages[1]
-            .iter()
-            .for_each(|page| println!("* {}", page));
-        changed_pages[2]
-            .iter()
-            .for_each(|page| println!("- {}", page));
+    let mut paths = vec![".svlmd".to_string()];
+    let version_source = file_manager.version_source();
+    if let VersionSource::Text { path } = &version_source {
+        if !file_manager.resolve(path)?.exists() {
+            let version = semver::Version::parse(DEFAULT_SEED_VERSION)?;
+            seed_version(file_manager, &version, false)?;
+        }
     }
+    if let Some(path) = version_source.path() {
+        paths.push(path.to_string());
+    }
+    paths.push(file_manager.page_repo_path(file_manager.contributor()));
 
-    // Create version page if it doesn't exist
-    if !file_manager.logseq_page_exists(&version_page) {
-        let now = Utc::now().format("%Y-%m-%d").to_string();
-
-        file_manager.write_logseq_page(&LogseqPage::new(
-            &version_page,
-            vec![
-                ("tags".into(), "Version".into()),
-                ("released-date".into(), now),
-            ],
-            vec![
-                ("# Summary".into(), 0),
-                ("".into(), 0),
-                ("# Changed Pages".into(), 0),
-            ],
-        ))?;
-    }
-
-    let mut page = file_manager.read_logseq_page(&version_page)?;
-    let full_version_string = format!("## [[{}]]", version);
-
-    // Find the "Changed Pages" section
-    let changed_pages_index = page
-        .contents
-        .iter()
-        .position(|(line, _)| line == "# Changed Pages")
-        .unwrap_or(0);
+    file_manager.commit(&paths, "Initialize SVLMD")?;
+    println!("Initialized git repository.");
+    Ok(())
+}
 
-    // Find the latest version entry after "Changed Pages"
-    let latest_version_index = page.contents[changed_pages_index..]
-        .iter()
-        .position(|(line, indent)| line.starts_with("## [[") && *indent == 1)
-        .map(|pos| pos + changed_pages_index);
+/// Title of the page created by the self-test
+const SELF_TEST_PAGE: &str = "Self Test/Page";
+
+/// Handle the self-test command
+///
+/// Builds a vault in a new temporary directory, which is removed afterwards
+/// whatever the outcome, so the user's vaults are never touched. Each step is
+/// reported as it passes, and the first failing step ends the test.
+fn self_test_command() -> Result<()> {
+    let dir = tempfile::Builder::new()
+        .prefix("svlmd-self-test-")
+        .tempdir()
+        .context("Failed to create a temporary directory")?;
+    println!("Running self-test in {}", dir.path().display());
+    let result = self_test_steps(dir.path());
+    let removed = dir.close();
+    result.context("Self-test failed")?;
+    removed.context("Failed to remove the temporary directory")?;
+    println!("Self-test passed");
+    Ok(())
+}
 
-    let mut new_entries = vec![(full_version_string.clone(), 1)];
-    let mut existing_changes = Vec::new();
+/// Run the self-test steps in the vault at `root`
+fn self_test_steps(root: &Path) -> Result<()> {
+    // Settings that a user-level configuration would otherwise provide are
+    // fixed, so it can't change the outcome
+    let config = serde_json::json!({
+        "contributor": "Self Test",
+        "email": "self-test@svlmd.invalid",
+        "pages_dir": "pages",
+        "ignore_pages": [],
+        "version_source": { "type": "text", "path": "version.txt" },
+    });
+    fs::write(root.join(".svlmd"), serde_json::to_string_pretty(&config)?)
+        .context("init: failed to write .svlmd")?;
+    let file_manager = init(root, false).context("init")?;
+    init_git(&file_manager).context("init")?;
+    if !file_manager.logseq_page_exists(file_manager.contributor()) {
+        bail!("init: author page '{}' missing", file_manager.contributor());
+    }
+    let version = version::read(&file_manager).context("init")?;
+    println!("init: ok");
 
-    if let Some(idx) = latest_version_index {
-        let next_version_index = page.contents[idx + 1..]
-            .iter()
-            .position(|(line, indent)| line.starts_with("## [[") && *indent == 1)
-            .map(|pos| pos + idx + 1)
-            .unwrap_or(page.contents.len());
-
-        // Check if the latest version matches current version
-        if page.contents[idx].0 == full_version_string {
-            // Accumulate changes from the existing version
-            for (line, indent) in &page.contents[idx..next_version_index] {
-                if line.starts_with("### ") || line.starts_with("[[") {
-                    existing_changes.push((line.clone(), *indent));
-                }
-            }
-            // Remove existing version entry as we'll merge it with new changes
-            page.contents.drain(idx..next_version_index);
+    new_command(&file_manager, SELF_TEST_PAGE, None).context("new")?;
+    file_manager
+        .read_logseq_page(SELF_TEST_PAGE)
+        .context("new: failed to read the created page")?;
+    file_manager
+        .stage(&[file_manager.page_repo_path(SELF_TEST_PAGE)])
+        .context("new")?;
+    let changed_pages = file_manager
+        .get_changed_pages(&ChangeArgs::default().options())
+        .context("new")?;
+    if changed_pages.added != [SELF_TEST_PAGE] {
+        bail!(
+            "new: expected '{}' to be the only added page, found {:?}",
+            SELF_TEST_PAGE,
+            changed_pages.added
+        );
+    }
+    println!("new: ok");
+
+    let Commands::Sync(args) = Cli::try_parse_from(["svlmd", "sync"])?.command else {
+        unreachable!();
+    };
+    sync_command(&file_manager, &args).context("sync")?;
+    let version_page = file_manager
+        .read_logseq_page(&changelog::version_page_title(&version))
+        .context("sync: failed to read the version page")?;
+    let recorded = changelog::recorded_pages(&version_page, &file_manager.changelog_headings());
+    if !recorded.added.iter().any(|page| page == SELF_TEST_PAGE) {
+        bail!(
+            "sync: '{}' isn't recorded as added on version page {}",
+            SELF_TEST_PAGE,
+            version_page.title
+        );
+    }
+    println!("sync: ok");
+    Ok(())
+}
+
+/// Print changed pages, marking them as added (+), modified (*), deleted (-),
+/// or conflicted (!)
+///
+/// The listing is followed by a summary line, which is printed alone if
+/// `count_only` is set.
+fn print_changed_pages(changed_pages: &ChangedPages, count_only: bool) {
+    if !count_only {
+        print_page_list(changed_pages);
+    }
+    println!("{}", changed_pages.summary());
+}
+
+/// Print changed pages in one section per author, followed by a summary line
+///
+/// Pages are attributed to the author of the last commit touching them. Pages
+/// no commit touched yet are attributed to the current contributor.
+fn print_changed_pages_by_author(
+    file_manager: &FileManager,
+    changed_pages: &ChangedPages,
+) -> Result<()> {
+    let authors = changed_page_authors(file_manager, changed_pages)?;
+    let author_of = |title: &String| authors[title].clone();
+
+    let mut by_author = BTreeMap::<String, ChangedPages>::new();
+    type Section = fn(&mut ChangedPages) -> &mut Vec<String>;
+    let sections: [(&Vec<String>, Section); 4] = [
+        (&changed_pages.added, |pages| &mut pages.added),
+        (&changed_pages.modified, |pages| &mut pages.modified),
+        (&changed_pages.deleted, |pages| &mut pages.deleted),
+        (&changed_pages.conflicted, |pages| &mut pages.conflicted),
+    ];
+    for (titles, section) in sections {
+        for title in titles {
+            section(by_author.entry(author_of(title)).or_default()).push(title.clone());
         }
     }
 
-    // Merge existing changes with new changes
-    let mut all_changes = Vec::new();
+    for (author, pages) in &by_author {
+        println!("{}:", author);
+        print_page_list(pages);
+    }
+    println!("{}", changed_pages.summary());
+    Ok(())
+}
 
-    // Process new changes
-    if !changed_pages.iter().all(|v| v.is_empty()) {
-        // Added files
-        let mut added = existing_changes
-            .iter()
-            .filter(|(line, indent)| *indent == 3 && line.starts_with("[["))
-            .filter(|(line, _)| {
-                let section_start = existing_changes
-                    .iter()
-                    .position(|(l, i)| *i == 2 && l == "### Added");
-                let section_end = existing_changes
-                    .iter()
-                    .position(|(l, i)| *i == 2 && l == "### Modified")
-                    .or_else(|| {
-                        existing_changes
-                            .iter()
-                            .position(|(l, i)| *i == 2 && l == "### Deleted")
-                    });
-                section_start
-                    .and_then(|start| section_end.map(|end| (start, end)))
-                    .map_or(false, |(start, end)| {
-                        existing_changes[start..end].iter().any(|(l, _)| l == line)
-                    })
-            })
-            .map(|(line, _)| line.clone())
-            .collect::<Vec<_>>();
+/// Attribute each changed page to the author of the last commit touching it
+///
+/// Pages no commit touched yet, such as new pages, are attributed to the
+/// configured contributor.
+fn changed_page_authors(
+    file_manager: &FileManager,
+    changed_pages: &ChangedPages,
+) -> Result<HashMap<String, String>> {
+    let titles = [
+        &changed_pages.added,
+        &changed_pages.modified,
+        &changed_pages.deleted,
+        &changed_pages.conflicted,
+    ]
+    .into_iter()
+    .flatten()
+    .cloned()
+    .collect::<Vec<_>>();
+    let mut authors = file_manager.last_authors(&titles)?;
+    for title in titles {
+        authors
+            .entry(title)
+            .or_insert_with(|| file_manager.contributor().to_string());
+    }
+    Ok(authors)
+}
+
+/// Keep only the changed pages attributed to `author`, ignoring case
+fn retain_author(
+    file_manager: &FileManager,
+    changed_pages: &mut ChangedPages,
+    author: &str,
+) -> Result<()> {
+    let authors = changed_page_authors(file_manager, changed_pages)?;
+    let author = author.trim().to_lowercase();
+    let sections = [
+        &mut changed_pages.added,
+        &mut changed_pages.modified,
+        &mut changed_pages.deleted,
+        &mut changed_pages.conflicted,
+    ];
+    for pages in sections {
+        pages.retain(|title| authors[title].to_lowercase() == author);
+    }
+    Ok(())
+}
 
-        added.extend(changed_pages[0].iter().map(|p| format!("[[{}]]", p)));
-        added.sort();
-        added.dedup();
+/// Print the changed pages one per line
+fn print_page_list(changed_pages: &ChangedPages) {
+    changed_pages
+        .added
+        .iter()
+        .for_each(|page| println!("+ {}", page));
+    changed_pages
+        .modified
+        .iter()
+        .for_each(|page| println!("* {}", page));
+    changed_pages
+        .deleted
+        .iter()
+        .for_each(|page| println!("- {}", page));
+    changed_pages
+        .conflicted
+        .iter()
+        .for_each(|page| println!("! {}", page));
+}
 
-        if !added.is_empty() {
-            all_changes.push(("### Added".to_string(), 2));
-            all_changes.extend(added.into_iter().map(|page| (page, 3)));
+/// Synchronize version information
+///
+/// Updates version tracking by:
+/// 1. Reading the current version from the configured source, unless
+///    `target_version` overrides it
+/// 2. Creating or updating the version page in Logseq
+/// 3. Tracking changed pages since the last version
+///
+/// Returns the version that was synced and the changes recorded in this sync.
+fn sync_version(
+    file_manager: &FileManager,
+    args: &SyncArgs,
+) -> Result<(semver::Version, ChangedPages)> {
+    let verbose = args.verbose;
+    let version = match &args.target_version {
+        Some(version) => version.clone(),
+        None => version::read(file_manager)?,
+    };
+    if verbose {
+        match args.target_version {
+            Some(_) => println!("Target version: {}", version),
+            None => println!(
+                "Found version: {} (from {})",
+                version,
+                file_manager.version_source()
+            ),
         }
+    }
 
-        // Modified files
-        let mut modified = existing_changes
-            .iter()
-            .filter(|(line, indent)| *indent == 3 && line.starts_with("[["))
-            .filter(|(line, _)| {
-                let section_start = existing_changes
-                    .iter()
-                    .position(|(l, i)| *i == 2 && l == "### Modified");
-                let section_end = existing_changes
-                    .iter()
-                    .position(|(l, i)| *i == 2 && l == "### Deleted")
-                    .or_else(|| existing_changes.iter().position(|(_, i)| *i == 1));
-                section_start
-                    .and_then(|start| section_end.map(|end| (start, end)))
-                    .map_or(false, |(start, end)| {
-                        existing_changes[start..end].iter().any(|(l, _)| l == line)
-                    })
-            })
-            .map(|(line, _)| line.clone())
-            .collect::<Vec<_>>();
+    let headings = file_manager.changelog_headings();
+    let version_page = changelog::version_page_title(&version);
+    args.changes.print_debug_status(file_manager)?;
+    let mut changed_pages = file_manager.get_changed_pages(&args.changes.options())?;
+    if let Some(author) = &args.author {
+        retain_author(file_manager, &mut changed_pages, author)?;
+    }
+
+    if verbose && args.group_by == GroupBy::Author {
+        print_changed_pages_by_author(file_manager, &changed_pages)?;
+    } else if verbose || args.count_only {
+        print_changed_pages(&changed_pages, args.count_only);
+    }
 
-        modified.extend(changed_pages[1].iter().map(|p| format!("[[{}]]", p)));
-        modified.sort();
-        modified.dedup();
+    let conflicted = &changed_pages.conflicted;
+    if !conflicted.is_empty() {
+        if args.strict {
+            bail!(
+                "{} pages have merge conflicts: {}",
+                conflicted.len(),
+                conflicted.join(", ")
+            );
+        }
+        eprintln!(
+            "Warning: pages with merge conflicts are left out of the changelog: {}",
+            conflicted.join(", ")
+        );
+    }
 
-        if !modified.is_empty() {
-            all_changes.push(("### Modified".to_string(), 2));
-            all_changes.extend(modified.into_iter().map(|page| (page, 3)));
+    // Create version page if it doesn't exist
+    let page = if file_manager.logseq_page_exists(&version_page) {
+        file_manager.read_logseq_page(&version_page)?
+    } else {
+        let now = file_manager.today()?;
+        let template = template::load(file_manager, template::VERSION_TEMPLATE)?;
+        let mut page = template::instantiate(&template, &version_page, &now);
+        let mut summary = Vec::new();
+        // The Summary in version.txt describes the current version only
+        if args.target_version.is_none() {
+            summary.extend(version::read_summary(file_manager)?);
+        }
+        if args.summary_from_commits {
+            summary.extend(commit_subjects_since_release(file_manager, &version)?);
+        }
+        // Each line is inserted as the first block under the heading
+        for line in summary.iter().rev() {
+            if let Err(e) = changelog::add_summary(&mut page, line, &headings) {
+                eprintln!("Warning: {:#}, skipping the Summary", e);
+                break;
+            }
         }
+        page
+    };
 
-        // Deleted files
-        let mut deleted = existing_changes
+    if !changelog::has_changed_pages_heading(&page, &headings) {
+        eprintln!(
+            "Warning: {} has no '{}' heading, appending one",
+            version_page, headings.changed_pages
+        );
+    }
+    let page = if args.force_recreate {
+        eprintln!(
+            "Warning: replacing all changes previously recorded for {}",
+            version
+        );
+        changelog::recreate_changed_pages(
+            &page,
+            &changed_pages,
+            &version,
+            &headings,
+            file_manager.changelog_order(),
+        )
+    } else {
+        changelog::merge_changed_pages(
+            &page,
+            &changed_pages,
+            &version,
+            &headings,
+            file_manager.changelog_order(),
+        )
+    };
+    let (page, overflow) = move_to_overflow(file_manager, page, &version, args.force_recreate)?;
+    let mut page = label_changelog(file_manager, &page, Some(&version));
+    let overflow =
+        overflow.map(|overflow| label_changelog(file_manager, &overflow, Some(&version)));
+    if args.report_only {
+        let entry_page = overflow.as_ref().unwrap_or(&page);
+        let entry = changelog::current_entry(entry_page, &version, &headings).unwrap_or_default();
+        let entry = entry
             .iter()
-            .filter(|(line, indent)| *indent == 3 && line.starts_with("[["))
-            .filter(|(line, _)| {
-                let section_start = existing_changes
-                    .iter()
-                    .position(|(l, i)| *i == 2 && l == "### Deleted");
-                let section_end = existing_changes
-                    .iter()
-                    .position(|(_, i)| *i == 1)
-                    .unwrap_or(existing_changes.len());
-                section_start.map_or(false, |start| {
-                    existing_changes[start..section_end]
-                        .iter()
-                        .any(|(l, _)| l == line)
-                })
-            })
-            .map(|(line, _)| line.clone())
+            .map(|(line, indent)| (line.clone(), indent.saturating_sub(1)))
             .collect::<Vec<_>>();
+        print!("{}", file_manager::format_contents(&entry));
+        return Ok((version, changed_pages));
+    }
 
-        deleted.extend(changed_pages[2].iter().map(|p| format!("[[{}]]", p)));
-        deleted.sort();
-        deleted.dedup();
-
-        if !deleted.is_empty() {
-            all_changes.push(("### Deleted".to_string(), 2));
-            all_changes.extend(deleted.into_iter().map(|page| (page, 3)));
+    if args.edit {
+        edit_summary(&mut page, &headings)?;
+    }
+    let mut pages = std::iter::once(page).chain(overflow).collect::<Vec<_>>();
+    if args.prune {
+        for page in &mut pages {
+            let pruned;
+            (*page, pruned) = changelog::prune_empty_sections(page, &headings);
+            if !pruned.is_empty() {
+                println!("{}: pruned {}", page.title, pruned);
+            }
         }
     }
+    for page in &pages {
+        file_manager.write_logseq_page(page)?;
+    }
+
+    write_version_tag_page(file_manager)?;
+
+    Ok((version, changed_pages))
+}
+
+/// Get the subjects of the commits since the last version tag before
+/// `version`, oldest first
+///
+/// All commits are included if there is no such tag.
+fn commit_subjects_since_release(
+    file_manager: &FileManager,
+    version: &semver::Version,
+) -> Result<Vec<String>> {
+    let previous = file_manager
+        .version_tags()?
+        .into_iter()
+        .rfind(|tag| tag.version < *version);
+    file_manager.commit_subjects(previous.map(|tag| tag.commit))
+}
+
+/// Move the pages of the current entry of a version page to its overflow page
+///
+/// `page` has the current changes recorded already. If the overflow page of
+/// the version exists, or the entry lists more pages than
+/// `max_changed_pages`, the pages of the entry are recorded on the overflow
+/// page (replacing its entry if `force_recreate` is set), and the entry links
+/// to it instead. Returns the version page and the overflow page, if any.
+fn move_to_overflow(
+    file_manager: &FileManager,
+    page: LogseqPage,
+    version: &semver::Version,
+    force_recreate: bool,
+) -> Result<(LogseqPage, Option<LogseqPage>)> {
+    let headings = file_manager.changelog_headings();
+    let overflow_title = changelog::overflow_page_title(version);
+    let recorded = changelog::entry_changes(&page, version, &headings).unwrap_or_default();
+    let overflow = if file_manager.logseq_page_exists(&overflow_title) {
+        let overflow = file_manager.read_logseq_page(&overflow_title)?;
+        if force_recreate {
+            changelog::recreate_changed_pages(
+                &overflow,
+                &recorded,
+                version,
+                &headings,
+                file_manager.changelog_order(),
+            )
+        } else {
+            changelog::merge_changed_pages(
+                &overflow,
+                &recorded,
+                version,
+                &headings,
+                file_manager.changelog_order(),
+            )
+        }
+    } else if file_manager
+        .max_changed_pages()
+        .is_some_and(|max| recorded.len() > max)
+    {
+        let overflow = LogseqPage::new(&overflow_title, vec![], vec![]);
+        changelog::merge_changed_pages(
+            &overflow,
+            &recorded,
+            version,
+            &headings,
+            file_manager.changelog_order(),
+        )
+    } else {
+        return Ok((page, None));
+    };
 
-    // Add all accumulated changes to new entries
-    new_entries.extend(all_changes);
+    let overflow_changes =
+        changelog::entry_changes(&overflow, version, &headings).unwrap_or_default();
+    let page = changelog::link_overflow(&page, version, &overflow_changes, &headings);
+    Ok((page, Some(overflow)))
+}
 
-    // Insert all new entries after the "Changed Pages" section
-    let insert_position = changed_pages_index + 1;
-    for (entry, indent) in new_entries.into_iter().rev() {
-        page.contents.insert(insert_position, (entry, indent));
+/// Label the pages listed in changelog entries as configured
+///
+/// With `changelog_links` set to `display`, the pages of the entry of
+/// `version`, or of all entries if `None`, are labelled with their display
+/// names. Otherwise the page is returned unchanged.
+fn label_changelog(
+    file_manager: &FileManager,
+    page: &LogseqPage,
+    version: Option<&semver::Version>,
+) -> LogseqPage {
+    if file_manager.changelog_links() != ChangelogLinks::Display {
+        return page.clone();
     }
+    changelog::label_links(page, version, &file_manager.changelog_headings(), |title| {
+        display_name(file_manager, title)
+    })
+}
 
-    // Write the updated page
-    file_manager.write_logseq_page(&page)?;
+/// Get the name a page is displayed with in changelogs
+///
+/// This is the page's `title::` property if it differs from the title, then
+/// the first name in its `alias::` property, then the last segment of a
+/// namespaced title. `None` if the page has no name other than its title.
+fn display_name(file_manager: &FileManager, title: &str) -> Option<String> {
+    // Deleted pages can't be read, and fall back to their title
+    let page = file_manager.read_logseq_page(title).ok();
+    let from_page = page.as_ref().and_then(|page| {
+        page.property("title")
+            .map(str::trim)
+            .filter(|display| !display.is_empty() && *display != title)
+            .map(str::to_string)
+            .or_else(|| {
+                page.property("alias")
+                    .and_then(|aliases| links::parse_page_list(aliases).into_iter().next())
+            })
+    });
+    from_page.or_else(|| {
+        title
+            .rsplit_once('/')
+            .map(|(_, last)| last.trim().to_string())
+            .filter(|last| !last.is_empty())
+    })
+}
+
+/// Edit the Summary section of a version page in the user's editor
+///
+/// The Summary blocks are shown one level shallower than on the page. The
+/// page is left unchanged if the editor exits without saving.
+fn edit_summary(page: &mut LogseqPage, headings: &ChangelogHeadings) -> Result<()> {
+    let Some(summary) = changelog::summary(page, headings) else {
+        eprintln!(
+            "Warning: {} has no '{}' heading, skipping --edit",
+            page.title, headings.summary
+        );
+        return Ok(());
+    };
+    let shallower = summary
+        .into_iter()
+        .map(|(line, indent)| (line, indent.saturating_sub(1)))
+        .collect::<Vec<_>>();
+
+    let Some(text) = Editor::new()
+        .extension(".md")
+        .edit(&file_manager::format_contents(&shallower))
+        .context("Failed to run editor")?
+    else {
+        println!("Summary not saved, keeping it unchanged");
+        return Ok(());
+    };
+    let edited = LogseqPage::from_plain(&page.title, vec![], text.trim_end())
+        .contents
+        .into_iter()
+        .map(|(line, indent)| {
+            let indent = if line.is_empty() {
+                0
+            } else {
+                indent.saturating_add(1)
+            };
+            (line, indent)
+        })
+        .collect();
+    changelog::replace_summary(page, edited, headings);
+    Ok(())
+}
 
-    // Modify the Version page
+/// Write the Version page that version pages are tagged with
+fn write_version_tag_page(file_manager: &FileManager) -> Result<()> {
     file_manager.write_logseq_page(&LogseqPage {
         title: "Version".into(),
         properties: vec![
@@ -310,42 +1402,1720 @@ fn sync_version(file_manager: &FileManager, verbose: bool) -> Result<()> {
             ("exclude-from-graph-view".into(), "true".into()),
         ],
         contents: vec![],
-    })?;
+    })
+}
 
-    Ok(())
+/// Rebuild the changelogs of all version pages from git tags
+///
+/// Walks the `v*` tags in semver order and writes each version's entry from
+/// the pages changed since the previous tag. The first tag is compared
+/// against the empty tree. Content outside the Changed Pages section, such
+/// as the Summary, is preserved.
+fn rebuild_command(file_manager: &FileManager) -> Result<()> {
+    let tags = file_manager.version_tags()?;
+    if tags.is_empty() {
+        bail!("No v* version tags found");
+    }
+
+    // Group entries by version page, keeping the latest release date
+    let headings = file_manager.changelog_headings();
+    let mut version_pages = Vec::<(String, Vec<_>, i64)>::new();
+    let mut previous = None;
+    for tag in &tags {
+        let changed_pages = file_manager.get_changed_pages_between(previous, tag.commit)?;
+        previous = Some(tag.commit);
+
+        let title = changelog::version_page_title(&tag.version);
+        let entry = changelog::version_entry(
+            &tag.version,
+            &changed_pages,
+            &headings,
+            file_manager.changelog_order(),
+        );
+        match version_pages.iter_mut().find(|(page, _, _)| *page == title) {
+            // Newest entries come first
+            Some((_, entries, time)) => {
+                entries.splice(0..0, entry);
+                *time = tag.time;
+            }
+            None => version_pages.push((title, entry, tag.time)),
+        }
+    }
+
+    for (title, entries, time) in version_pages {
+        let mut page = if file_manager.logseq_page_exists(&title) {
+            file_manager.read_logseq_page(&title)?
+        } else {
+            let released_date = file_manager
+                .format_date(chrono::DateTime::from_timestamp(time, 0).unwrap_or_default())?;
+            let template = template::load(file_manager, template::VERSION_TEMPLATE)?;
+            template::instantiate(&template, &title, &released_date)
+        };
+        changelog::replace_changed_pages(&mut page, entries, &headings);
+        let page = label_changelog(file_manager, &page, None);
+        file_manager.write_logseq_page(&page)?;
+        println!("Rebuilt {}", title);
+    }
+
+    write_version_tag_page(file_manager)
 }
 
 /// Handle the sync command
 ///
 /// Processes synchronization operations based on provided flags
-fn sync_command(file_manager: &FileManager, version: bool, verbose: bool) -> Result<()> {
-    let mut version = version;
+fn sync_command(file_manager: &FileManager, args: &SyncArgs) -> Result<()> {
+    let mut version = args.version;
     if !version {
         version = true;
     }
 
     if version {
-        sync_version(file_manager, verbose)?;
+        let (synced, changed_pages) = sync_version(file_manager, args)?;
+        if args.commit {
+            file_manager.commit(
+                &version_page_paths(file_manager, &synced),
+                &format!("Sync version {}", synced),
+            )?;
+            println!("Committed version {}", synced);
+        }
+        if args.manifest {
+            write_sync_manifest(file_manager, &synced, &changed_pages)?;
+        }
     }
     Ok(())
 }
 
-/// Main entry point for the SVLMD CLI tool
+/// Write the manifest of a sync
+///
+/// The manifest is replaced atomically, so readers never see a partial file.
+fn write_sync_manifest(
+    file_manager: &FileManager,
+    version: &semver::Version,
+    changed_pages: &ChangedPages,
+) -> Result<()> {
+    let manifest = SyncManifest {
+        version: version.to_string(),
+        synced_at: chrono::Utc::now().to_rfc3339(),
+        added: &changed_pages.added,
+        modified: &changed_pages.modified,
+        deleted: &changed_pages.deleted,
+    };
+    let json = serde_json::to_string_pretty(&manifest)? + "\n";
+    file_manager.write_atomic(SYNC_MANIFEST, json)
+}
+
+/// Get the repository paths of the pages written when syncing a version
+fn version_page_paths(file_manager: &FileManager, version: &semver::Version) -> Vec<String> {
+    let mut paths = vec![
+        file_manager.page_repo_path(&changelog::version_page_title(version)),
+        file_manager.page_repo_path("Version"),
+    ];
+    let overflow = changelog::overflow_page_title(version);
+    if file_manager.logseq_page_exists(&overflow) {
+        paths.push(file_manager.page_repo_path(&overflow));
+    }
+    paths
+}
+
+/// Handle the release command
+///
+/// Refuses to release with uncommitted changes outside the pages directory
+/// unless `allow_dirty` is set. Then syncs the version, commits the staged
+/// changes with the version pages and version.txt, and tags the commit.
+fn release_command(file_manager: &FileManager, args: &ReleaseArgs) -> Result<()> {
+    let version_source = file_manager.version_source();
+    let version_path = version_source.path();
+    // The manifest of the last sync is meant to stay out of the repository
+    let mut allowed = vec![SYNC_MANIFEST];
+    allowed.extend(version_path);
+    let dirty = file_manager.dirty_paths_outside_pages(&allowed)?;
+    if !dirty.is_empty() {
+        dirty
+            .iter()
+            .for_each(|path| eprintln!("Uncommitted: {}", path));
+        if !args.allow_dirty {
+            bail!(
+                "{} uncommitted changes outside pages, commit them or pass --allow-dirty",
+                dirty.len()
+            );
+        }
+        eprintln!("Warning: releasing with uncommitted changes");
+    }
+
+    // Only staged page edits are committed with the release
+    let unstaged = file_manager.unstaged_pages()?;
+    if !unstaged.is_empty() {
+        unstaged
+            .iter()
+            .for_each(|title| eprintln!("Unstaged: {}", title));
+        if args.strict {
+            bail!(
+                "{} pages with unstaged edits, stage or commit them first",
+                unstaged.len()
+            );
+        }
+        eprintln!("Warning: unstaged page edits are left out of the release");
+    }
+
+    let (version, _) = sync_version(
+        file_manager,
+        &SyncArgs {
+            verbose: args.verbose,
+            strict: args.strict,
+            ..Default::default()
+        },
+    )?;
+
+    let mut paths = version_page_paths(file_manager, &version);
+    paths.extend(version_path.map(String::from));
+    file_manager.commit(&paths, &format!("Release {}", version))?;
+    file_manager.tag_version(&version)?;
+    println!("Released {}", version);
+    Ok(())
+}
+
+/// Handle the status command
+///
+/// The porcelain format prints a code (`A`, `M`, `D` or `U`) and the title of each
+/// changed page, with no other output. It is kept stable for scripts.
+fn status_command(file_manager: &FileManager, args: &StatusArgs) -> Result<()> {
+    args.changes.print_debug_status(file_manager)?;
+    let mut changed_pages = file_manager.get_changed_pages(&args.changes.options())?;
+    if !args.only.is_empty() {
+        retain_kinds(&mut changed_pages, &args.only);
+    }
+    if let Some(author) = &args.author {
+        retain_author(file_manager, &mut changed_pages, author)?;
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&changed_pages)?);
+    } else if args.porcelain {
+        let codes = [
+            ('A', &changed_pages.added),
+            ('M', &changed_pages.modified),
+            ('D', &changed_pages.deleted),
+            ('U', &changed_pages.conflicted),
+        ];
+        for (code, pages) in codes {
+            pages.iter().for_each(|page| println!("{} {}", code, page));
+        }
+    } else if changed_pages.is_empty() && !args.count_only {
+        println!("No changed pages");
+    } else if args.group_by == GroupBy::Author {
+        print_changed_pages_by_author(file_manager, &changed_pages)?;
+    } else {
+        print_changed_pages(&changed_pages, args.count_only);
+    }
+    Ok(())
+}
+
+/// Clear the changed pages of all kinds not in `kinds`
+fn retain_kinds(changed_pages: &mut ChangedPages, kinds: &[ChangeKind]) {
+    let sections = [
+        (ChangeKind::Added, &mut changed_pages.added),
+        (ChangeKind::Modified, &mut changed_pages.modified),
+        (ChangeKind::Deleted, &mut changed_pages.deleted),
+        (ChangeKind::Conflicted, &mut changed_pages.conflicted),
+    ];
+    for (kind, pages) in sections {
+        if !kinds.contains(&kind) {
+            pages.clear();
+        }
+    }
+}
+
+/// Handle the unstaged command
+fn unstaged_command(file_manager: &FileManager, strict: bool) -> Result<()> {
+    let unstaged = file_manager.unstaged_pages()?;
+    if unstaged.is_empty() {
+        println!("No pages with unstaged edits");
+        return Ok(());
+    }
+    unstaged.iter().for_each(|title| println!("{}", title));
+    if strict {
+        bail!("{} pages with unstaged edits", unstaged.len());
+    }
+    Ok(())
+}
+
+/// Handle the watch command
+///
+/// Prints the status after each burst of page changes, or with `sync`,
+/// syncs the version and prints a summary of the recorded changes
+fn watch_command(file_manager: &FileManager, sync: bool, debounce: Duration) -> Result<()> {
+    if !sync {
+        return watch::watch(file_manager, Vec::new, debounce, || {
+            println!();
+            status_command(file_manager, &StatusArgs::default())
+        });
+    }
+
+    // The pages written by a sync, whose changes mustn't trigger another
+    let written_pages = || {
+        let mut titles = vec!["Version".to_string()];
+        if let std::result::Result::Ok(version) = version::read(file_manager) {
+            titles.push(changelog::version_page_title(&version));
+            titles.push(changelog::overflow_page_title(&version));
+        }
+        titles
+    };
+    watch::watch(file_manager, written_pages, debounce, || {
+        let (version, changed_pages) = sync_version(file_manager, &SyncArgs::default())?;
+        println!("Synced {}: {}", version, changed_pages.summary());
+        Ok(())
+    })
+}
+
+/// Handle the new command
+///
+/// Creates an empty page, or instantiates the named template
+fn new_command(file_manager: &FileManager, title: &str, template: Option<&str>) -> Result<()> {
+    if let Some(existing) = file_manager.find_page_title(title) {
+        bail!("Page '{}' already exists", existing);
+    }
+
+    let page = match template {
+        Some(name) => {
+            let now = file_manager.today()?;
+            let page = template::instantiate(&template::load(file_manager, name)?, title, &now);
+            println!("Using template: {}", name);
+            page
+        }
+        None => LogseqPage::new(title, vec![], vec![]),
+    };
+
+    file_manager.write_logseq_page(&page)?;
+    println!("Created page: {}", title);
+    Ok(())
+}
+
+/// Handle the backlinks command
+///
+/// Lists the pages with a link resolving to the given page, through aliases
+fn backlinks_command(file_manager: &FileManager, title: &str) -> Result<()> {
+    let pages = file_manager
+        .list_pages()?
+        .iter()
+        .map(|title| file_manager.read_logseq_page(title))
+        .collect::<Result<Vec<_>>>()?;
+    let aliases = AliasMap::new(&pages);
+    let Some(target) = aliases.resolve(title) else {
+        bail!("Page '{}' not found", title);
+    };
+
+    for page in &pages {
+        if aliases.linked_pages(page).contains(target) {
+            println!("{}", page.title);
+        }
+    }
+    Ok(())
+}
+
+/// Handle the hubs command
+///
+/// Ranks pages by the number of other pages linking to them, through aliases.
+/// Version pages and their overflow pages aren't counted, as their changelogs
+/// link to every changed page.
+fn hubs_command(file_manager: &FileManager, top: usize, json: bool) -> Result<()> {
+    let pages = file_manager
+        .list_pages()?
+        .iter()
+        .map(|title| file_manager.read_logseq_page(title))
+        .collect::<Result<Vec<_>>>()?;
+    let aliases = AliasMap::new(&pages);
+
+    let mut counts = HashMap::<&str, usize>::new();
+    for page in &pages {
+        if page.has_tag("Version") || changelog::overflow_page_version(&page.title).is_some() {
+            continue;
+        }
+        for target in aliases.linked_pages(page) {
+            if target != page.title {
+                *counts.entry(target).or_default() += 1;
+            }
+        }
+    }
+    let mut hubs = counts
+        .into_iter()
+        .map(|(title, links)| Hub {
+            title: title.to_string(),
+            links,
+        })
+        .collect::<Vec<_>>();
+    hubs.sort_by(|a, b| b.links.cmp(&a.links).then_with(|| a.title.cmp(&b.title)));
+    hubs.truncate(top);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&hubs)?);
+    } else {
+        for hub in &hubs {
+            println!("{} ({})", hub.title, hub.links);
+        }
+    }
+    Ok(())
+}
+
+/// Handle the rename command
+///
+/// Moves the page file and rewrites the links and tags referring to it. With
+/// `dry_run`, only reports the move and the lines that would change.
+fn rename_command(
+    file_manager: &FileManager,
+    from: &str,
+    to: &str,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let rename = file_manager.plan_rename(from, to, true)?;
+    println!(
+        "Move {} -> {}",
+        file_manager.page_repo_path(&rename.from),
+        file_manager.page_repo_path(to)
+    );
+    for (page, changed) in &rename.pages {
+        for line in changed {
+            println!("{}:{}:", page.title, line.line);
+            println!("- {}", line.before);
+            println!("+ {}", line.after);
+        }
+    }
+
+    let pages = rename.pages_updated();
+    if dry_run {
+        println!(
+            "Would rename '{}' to '{}' and update {} references in {} pages",
+            rename.from,
+            to,
+            rename.references(),
+            pages
+        );
+        return Ok(());
+    }
+    if !confirm_writes(yes, "rewrite", rename.pages.len())? {
+        return Ok(());
+    }
+
+    let references = file_manager.rename_logseq_page(&rename.from, to, true)?;
+    println!(
+        "Renamed '{}' to '{}' and updated {} references in {} pages",
+        rename.from, to, references, pages
+    );
+    Ok(())
+}
+
+/// Handle the import command
+///
+/// Reads every `.md` file directly in `dir`, titled by its filename with
+/// `___` read as `/`, as in page filenames. Pages that already exist are
+/// skipped unless `overwrite` is set, in which case they are replaced after
+/// confirmation.
+fn import_command(
+    file_manager: &FileManager,
+    dir: &Path,
+    overwrite: bool,
+    yes: bool,
+) -> Result<()> {
+    let mut files = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()
+        .with_context(|| format!("Failed to read {}", dir.display()))?;
+    files.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "md"));
+    files.sort();
+
+    let mut pages = Vec::new();
+    let mut skipped = 0;
+    let mut replaced = 0;
+    for path in &files {
+        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            eprintln!(
+                "Warning: skipping {}: filename isn't valid UTF-8",
+                path.display()
+            );
+            skipped += 1;
+            continue;
+        };
+        let mut title = stem.replace("___", "/");
+        if let Some(existing) = file_manager.find_page_title(&title) {
+            if !overwrite {
+                println!(
+                    "Skipped {}: page '{}' already exists",
+                    path.display(),
+                    existing
+                );
+                skipped += 1;
+                continue;
+            }
+            // Keep the existing file rather than adding one differing in case
+            title = existing;
+            replaced += 1;
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        pages.push(import::from_markdown(&title, &text));
+    }
+
+    if !confirm_writes(yes, "overwrite", replaced)? {
+        return Ok(());
+    }
+    for page in &pages {
+        file_manager.write_logseq_page(page)?;
+    }
+    println!("Imported {} pages, skipped {}", pages.len(), skipped);
+    Ok(())
+}
+
+/// Handle the rename-map command
+///
+/// Nothing is renamed unless the whole map is valid.
+fn rename_map_command(
+    file_manager: &FileManager,
+    file: &Path,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let entries = rename_map::load(file)?;
+    let rename = file_manager.plan_batch_rename(&entries)?;
+    for ((from, to), references) in rename.renames.iter().zip(&rename.references) {
+        println!(
+            "Move {} -> {} ({} references)",
+            file_manager.page_repo_path(from),
+            file_manager.page_repo_path(to),
+            references
+        );
+    }
+
+    let references = rename.references.iter().sum::<usize>();
+    let pages = rename.pages_updated();
+    if dry_run {
+        println!(
+            "Would rename {} pages and update {} references in {} pages",
+            rename.renames.len(),
+            references,
+            pages
+        );
+        return Ok(());
+    }
+    if !confirm_writes(yes, "rewrite", rename.pages.len())? {
+        return Ok(());
+    }
+
+    file_manager.batch_rename(&rename)?;
+    println!(
+        "Renamed {} pages and updated {} references in {} pages",
+        rename.renames.len(),
+        references,
+        pages
+    );
+    Ok(())
+}
+
+/// Handle the alias command
+///
+/// Refuses to add an alias that is already the title or an alias of another
+/// page, so links stay unambiguous.
+fn alias_command(
+    file_manager: &FileManager,
+    title: &str,
+    action: AliasAction,
+    alias: &str,
+) -> Result<()> {
+    let pages = file_manager
+        .list_pages()?
+        .iter()
+        .map(|title| file_manager.read_logseq_page(title))
+        .collect::<Result<Vec<_>>>()?;
+    let aliases = AliasMap::new(&pages);
+    let Some(mut page) = pages
+        .into_iter()
+        .find(|page| page.title.to_lowercase() == title.to_lowercase())
+    else {
+        bail!("Page '{}' not found", title);
+    };
+
+    let alias = alias.trim();
+    let mut values = page
+        .property("alias")
+        .map(links::parse_page_list)
+        .unwrap_or_default();
+    let position = values
+        .iter()
+        .position(|value| value.to_lowercase() == alias.to_lowercase());
+    match action {
+        AliasAction::Add => {
+            if let Some(other) = aliases.resolve(alias).filter(|other| *other != page.title) {
+                bail!("'{}' already refers to page '{}'", alias, other);
+            }
+            if position.is_some() {
+                println!("'{}' is already an alias of '{}'", alias, page.title);
+                return Ok(());
+            }
+            values.push(alias.to_string());
+        }
+        AliasAction::Remove => {
+            let Some(position) = position else {
+                bail!("'{}' is not an alias of '{}'", alias, page.title);
+            };
+            values.remove(position);
+        }
+    }
+
+    if values.is_empty() {
+        page.remove_property("alias");
+    } else {
+        page.set_property("alias", &values.join(", "));
+    }
+    file_manager.write_logseq_page(&page)?;
+    match action {
+        AliasAction::Add => println!("Added alias '{}' to '{}'", alias, page.title),
+        AliasAction::Remove => println!("Removed alias '{}' from '{}'", alias, page.title),
+    }
+    Ok(())
+}
+
+/// Handle the retag command
+///
+/// Pages must match all given filters. Pages already carrying the tag are
+/// left alone.
+fn retag_command(
+    file_manager: &FileManager,
+    tag: &str,
+    title_glob: Option<&str>,
+    content_contains: Option<&str>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        bail!("Tag cannot be empty");
+    }
+    let title_glob = title_glob
+        .map(|glob| {
+            glob::Pattern::new(glob).with_context(|| format!("Invalid title glob '{}'", glob))
+        })
+        .transpose()?;
+    let content_contains = content_contains.map(str::to_lowercase);
+
+    let mut tagged = Vec::new();
+    for title in file_manager.list_pages()? {
+        if title_glob
+            .as_ref()
+            .is_some_and(|pattern| !pattern.matches(&title))
+        {
+            continue;
+        }
+        let mut page = file_manager.read_logseq_page(&title)?;
+        if let Some(text) = &content_contains {
+            let matches = page
+                .contents
+                .iter()
+                .any(|(content, _)| content.to_lowercase().contains(text));
+            if !matches {
+                continue;
+            }
+        }
+        if page.has_tag(tag) {
+            continue;
+        }
+
+        // Append to the existing value to keep its formatting
+        let tags = match page.property("tags").filter(|tags| !tags.is_empty()) {
+            Some(tags) => format!("{}, {}", tags, tag),
+            None => tag.to_string(),
+        };
+        page.set_property("tags", &tags);
+        println!("{}", page.title);
+        tagged.push(page);
+    }
+
+    if dry_run {
+        println!("Would tag {} pages with '{}'", tagged.len(), tag);
+        return Ok(());
+    }
+    if !confirm_writes(yes, "tag", tagged.len())? {
+        return Ok(());
+    }
+    for page in &tagged {
+        file_manager.write_logseq_page(page)?;
+    }
+    println!("Tagged {} pages with '{}'", tagged.len(), tag);
+    Ok(())
+}
+
+/// Handle the stats command
+fn stats_command(file_manager: &FileManager, format: OutputFormat, json: bool) -> Result<()> {
+    let pages = file_manager
+        .list_pages()?
+        .iter()
+        .map(|title| file_manager.read_logseq_page(title))
+        .collect::<Result<Vec<_>>>()?;
+    let stats = collect_stats(&pages);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+    if format == OutputFormat::Table {
+        let mut counts = Table::new(&["Statistic", "Value"]);
+        for (name, value) in [
+            ("Pages", stats.pages),
+            ("Blocks", stats.blocks),
+            ("Links", stats.links),
+            ("Untagged pages", stats.untagged),
+        ] {
+            counts.push(vec![name.to_string(), value.to_string()]);
+        }
+        counts.print();
+        if !stats.tags.is_empty() {
+            println!();
+            let mut tags = Table::new(&["Tag", "Pages"]);
+            for TagCount { tag, count } in &stats.tags {
+                tags.push(vec![tag.clone(), count.to_string()]);
+            }
+            tags.print();
+        }
+        return Ok(());
+    }
+    println!("Pages: {}", stats.pages);
+    println!("Blocks: {}", stats.blocks);
+    println!("Links: {}", stats.links);
+    println!("Untagged pages: {}", stats.untagged);
+    if !stats.tags.is_empty() {
+        println!();
+        println!("Tags:");
+        let width = stats.tags[0].count.to_string().len();
+        for TagCount { tag, count } in &stats.tags {
+            println!("  {:>width$}  {}", count, tag, width = width);
+        }
+    }
+    Ok(())
+}
+
+/// Compute the statistics of the pages of a vault
+///
+/// Tags are counted ignoring case, under the spelling seen first. Blank lines
+/// and empty bullets aren't counted as blocks.
+fn collect_stats(pages: &[LogseqPage]) -> Stats {
+    let mut tags: Vec<TagCount> = Vec::new();
+    for tag in pages.iter().flat_map(LogseqPage::tags) {
+        match tags.iter_mut().find(|t| t.tag.eq_ignore_ascii_case(&tag)) {
+            Some(t) => t.count += 1,
+            None => tags.push(TagCount { tag, count: 1 }),
+        }
+    }
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+
+    Stats {
+        pages: pages.len(),
+        blocks: pages
+            .iter()
+            .flat_map(|page| &page.contents)
+            .filter(|(line, _)| !file_manager::is_blank(line))
+            .count(),
+        links: pages
+            .iter()
+            .flat_map(|page| &page.contents)
+            .map(|(line, _)| links::extract_links(line).len())
+            .sum(),
+        untagged: pages.iter().filter(|page| page.tags().is_empty()).count(),
+        tags,
+    }
+}
+
+/// Handle the doctor command
+///
+/// Fails if any check reports an error. With `fix`, missing author pages are
+/// recreated first.
+fn doctor_command(file_manager: &FileManager, json: bool, fix: bool) -> Result<()> {
+    if fix {
+        for name in doctor::fix_author_pages(file_manager)? {
+            // Keep stdout parseable in JSON mode
+            eprintln!("Recreated author page {}", name);
+        }
+    }
+    let checks = doctor::run(file_manager);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        checks.iter().for_each(|check| println!("{}", check));
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == doctor::Status::Error)
+        .count();
+    if failed > 0 {
+        bail!("{} critical checks failed", failed);
+    }
+    Ok(())
+}
+
+/// Handle the list command
+fn list_command(file_manager: &FileManager, args: &ListArgs) -> Result<()> {
+    let pagination = &args.pagination;
+    let (format, json, json_lines) = (args.format, args.json, args.json_lines);
+    let fields = args.fields.as_slice();
+    let mut titles = match &args.since_version {
+        Some(version) => added_since(file_manager, version)?,
+        None => file_manager.list_pages()?,
+    };
+    titles.retain(|title| !file_manager.is_ignored_page(title));
+    if let Some(depth) = args.depth {
+        return list_namespaces(&titles, depth, pagination, format, json, json_lines);
+    }
+    if json_lines {
+        let fields = if fields.is_empty() {
+            &[ListField::Title]
+        } else {
+            fields
+        };
+        let mut stream = JsonLines::new(pagination);
+        for title in &titles {
+            let values = page_fields(file_manager, title, fields)?;
+            if !stream.emit(&fields_object(fields, values))? {
+                break;
+            }
+        }
+        return Ok(());
+    }
+    let total = titles.len();
+    let titles = pagination.apply(titles);
+
+    if json && fields.is_empty() {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&pagination.envelope(total, &titles))?
+        );
+    } else if json {
+        let mut items = Vec::new();
+        for title in &titles {
+            let values = page_fields(file_manager, title, fields)?;
+            items.push(fields_object(fields, values));
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&pagination.envelope(total, &items))?
+        );
+    } else if format == OutputFormat::Table {
+        let fields = if fields.is_empty() {
+            &ListField::TABLE_DEFAULT
+        } else {
+            fields
+        };
+        let headers = fields
+            .iter()
+            .map(|field| field.header())
+            .collect::<Vec<_>>();
+        let mut table = Table::new(&headers);
+        for title in &titles {
+            let values = page_fields(file_manager, title, fields)?;
+            table.push(values.iter().map(field_text).collect());
+        }
+        table.print();
+    } else if fields.is_empty() {
+        titles.iter().for_each(|title| println!("{}", title));
+    } else {
+        for title in &titles {
+            let values = page_fields(file_manager, title, fields)?;
+            let line = values.iter().map(field_text).collect::<Vec<_>>();
+            println!("{}", line.join("\t"));
+        }
+    }
+    Ok(())
+}
+
+/// Get the values of the given fields of a page, in order
+///
+/// The page file is only read if a field comes from its properties, and then
+/// only up to the end of its properties.
+fn page_fields(
+    file_manager: &FileManager,
+    title: &str,
+    fields: &[ListField],
+) -> Result<Vec<serde_json::Value>> {
+    let needs_properties = fields
+        .iter()
+        .any(|field| matches!(field, ListField::Tags | ListField::CreatedDate));
+    let page = if needs_properties {
+        file_manager.read_page_properties(title)?
+    } else {
+        LogseqPage::new(title, vec![], vec![])
+    };
+    let mut values = Vec::new();
+    for field in fields {
+        values.push(match field {
+            ListField::Title => serde_json::Value::from(title),
+            ListField::Tags => serde_json::Value::from(page.tags()),
+            ListField::CreatedDate => page
+                .property("created-date")
+                .map_or(serde_json::Value::Null, serde_json::Value::from),
+            ListField::LastModified => match page_modified(file_manager, title) {
+                Some(modified) => file_manager.format_date(modified.into())?.into(),
+                None => serde_json::Value::Null,
+            },
+        });
+    }
+    Ok(values)
+}
+
+/// Build a JSON object of field values keyed by field name
+fn fields_object(fields: &[ListField], values: Vec<serde_json::Value>) -> serde_json::Value {
+    let object = fields
+        .iter()
+        .map(|field| field.name().to_string())
+        .zip(values)
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::Value::Object(object)
+}
+
+/// Format a field value as text, joining lists with `, ` and leaving
+/// missing values empty
+fn field_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(items) => {
+            items.iter().map(field_text).collect::<Vec<_>>().join(", ")
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Get the modification time of a page file, if it can be read
+fn page_modified(file_manager: &FileManager, title: &str) -> Option<SystemTime> {
+    let page = LogseqPage::new(title, vec![], vec![]);
+    fs::metadata(page.title_to_path(&file_manager.pages_dir()))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Get the pages recorded as added by the version pages at or after `since`
+///
+/// Titles are deduplicated ignoring case and sorted.
+fn added_since(file_manager: &FileManager, since: &semver::Version) -> Result<Vec<String>> {
+    let since_page = changelog::version_page_title(since);
+    if !file_manager.logseq_page_exists(&since_page) {
+        bail!("Version page '{}' not found", since_page);
+    }
+
+    let mut versions = Vec::new();
+    for title in file_manager.list_pages()? {
+        let (version, overflow) = match semver::Version::parse(&title) {
+            std::result::Result::Ok(version) => (version, false),
+            Err(_) => match changelog::overflow_page_version(&title) {
+                Some(version) => (version, true),
+                None => continue,
+            },
+        };
+        if version >= *since {
+            let page = file_manager.read_logseq_page(&title)?;
+            if overflow || page.has_tag("Version") {
+                versions.push((version, page));
+            }
+        }
+    }
+    versions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let headings = file_manager.changelog_headings();
+    let mut seen = HashSet::new();
+    let mut added = versions
+        .iter()
+        .flat_map(|(_, page)| changelog::recorded_pages(page, &headings).added)
+        .filter(|title| seen.insert(title.to_lowercase()))
+        .collect::<Vec<_>>();
+    added.sort();
+    Ok(added)
+}
+
+/// Print the namespaces of the titles collapsed to `depth` levels
+fn list_namespaces(
+    titles: &[String],
+    depth: u8,
+    pagination: &PaginationArgs,
+    format: OutputFormat,
+    json: bool,
+    json_lines: bool,
+) -> Result<()> {
+    let mut counts = BTreeMap::new();
+    for title in titles {
+        *counts
+            .entry(file_manager::namespace_prefix(title, depth as usize))
+            .or_insert(0) += 1;
+    }
+    if json_lines {
+        let mut stream = JsonLines::new(pagination);
+        for (title, pages) in counts {
+            let title = title.to_string();
+            if !stream.emit(&NamespaceCount { title, pages })? {
+                break;
+            }
+        }
+        return Ok(());
+    }
+    let total = counts.len();
+    let namespaces = pagination.apply(
+        counts
+            .into_iter()
+            .map(|(title, pages)| NamespaceCount {
+                title: title.to_string(),
+                pages,
+            })
+            .collect(),
+    );
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&pagination.envelope(total, &namespaces))?
+        );
+    } else if format == OutputFormat::Table {
+        let mut table = Table::new(&["Namespace", "Pages"]);
+        for namespace in namespaces {
+            table.push(vec![namespace.title, namespace.pages.to_string()]);
+        }
+        table.print();
+    } else {
+        for namespace in namespaces {
+            println!("{} ({})", namespace.title, namespace.pages);
+        }
+    }
+    Ok(())
+}
+
+/// Handle the search command
+///
+/// Matches page titles and content lines containing the query, ignoring case.
+/// With `json_lines`, each match is printed as soon as it's found.
+fn search_command(
+    file_manager: &FileManager,
+    query: &str,
+    pagination: &PaginationArgs,
+    json: bool,
+    json_lines: bool,
+) -> Result<()> {
+    let query = query.to_lowercase();
+    let mut stream = json_lines.then(|| JsonLines::new(pagination));
+    let mut matches = Vec::new();
+    // Returns false once no further matches are wanted
+    let mut found = |found: SearchMatch| match &mut stream {
+        Some(stream) => stream.emit(&found),
+        None => {
+            matches.push(found);
+            Ok(true)
+        }
+    };
+    for title in file_manager.list_pages()? {
+        if title.to_lowercase().contains(&query)
+            && !found(SearchMatch {
+                title: title.clone(),
+                line: None,
+                text: title.clone(),
+            })?
+        {
+            return Ok(());
+        }
+        let page = file_manager.read_logseq_page(&title)?;
+        for (i, (content, _)) in page.contents.iter().enumerate() {
+            // Block properties are on the lines following the block text
+            for (offset, line) in content.lines().enumerate() {
+                if line.to_lowercase().contains(&query)
+                    && !found(SearchMatch {
+                        title: title.clone(),
+                        line: Some(page.line_number(i) + offset),
+                        text: line.to_string(),
+                    })?
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    if json_lines {
+        return Ok(());
+    }
+    let total = matches.len();
+    let matches = pagination.apply(matches);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&pagination.envelope(total, &matches))?
+        );
+    } else {
+        for found in &matches {
+            match found.line {
+                Some(line) => println!("{}:{}: {}", found.title, line, found.text),
+                None => println!("{}", found.title),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle the unrecorded command
+///
+/// Lists pages on disk that no version page records as added
+fn unrecorded_command(file_manager: &FileManager, sort: PageOrder, json: bool) -> Result<()> {
+    let pages = file_manager
+        .list_pages()?
+        .iter()
+        .map(|title| file_manager.read_logseq_page(title))
+        .collect::<Result<Vec<_>>>()?;
+
+    let headings = file_manager.changelog_headings();
+    let recorded = pages
+        .iter()
+        .filter(|page| {
+            page.has_tag("Version") || changelog::overflow_page_version(&page.title).is_some()
+        })
+        .flat_map(|page| changelog::recorded_pages(page, &headings).added)
+        .map(|title| title.to_lowercase())
+        .collect::<HashSet<_>>();
+
+    let mut unrecorded = pages
+        .iter()
+        .filter(|page| !page.has_tag("Version") && page.title != "Version")
+        .filter(|page| changelog::overflow_page_version(&page.title).is_none())
+        .filter(|page| !recorded.contains(&page.title.to_lowercase()))
+        .map(|page| page.title.clone())
+        .collect::<Vec<_>>();
+
+    if sort == PageOrder::Modified {
+        unrecorded
+            .sort_by_cached_key(|title| std::cmp::Reverse(page_modified(file_manager, title)));
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&unrecorded)?);
+    } else {
+        unrecorded.iter().for_each(|title| println!("{}", title));
+    }
+    Ok(())
+}
+
+/// Handle the dedupe-changelog command
+fn dedupe_changelog_command(
+    file_manager: &FileManager,
+    title: Option<&str>,
+    yes: bool,
+) -> Result<()> {
+    let titles = match title {
+        Some(title) => vec![file_manager.resolve_page(title)?],
+        None => file_manager.list_pages()?,
+    };
+
+    let headings = file_manager.changelog_headings();
+    let mut total = 0;
+    let mut deduped = Vec::new();
+    for title in titles {
+        let page = file_manager.read_logseq_page(&title)?;
+        if !page.has_tag("Version") {
+            continue;
+        }
+        let (page, removed) = changelog::dedupe_changed_pages(&page, &headings);
+        if removed > 0 {
+            println!("{}: {} duplicates", title, removed);
+            deduped.push(page);
+            total += removed;
+        }
+    }
+
+    if !confirm_writes(yes, "rewrite", deduped.len())? {
+        return Ok(());
+    }
+    for page in &deduped {
+        file_manager.write_logseq_page(page)?;
+    }
+    println!("Removed {} duplicate entries", total);
+    Ok(())
+}
+
+/// Ask for confirmation before a command writes `count` pages
+///
+/// Returns whether to go ahead, which is always the case with `yes` or when
+/// there's nothing to write. Fails if stdin isn't a terminal, as nobody could
+/// answer.
+fn confirm_writes(yes: bool, action: &str, count: usize) -> Result<bool> {
+    if yes || count == 0 {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!(
+            "Refusing to {} {} pages without confirmation, pass --yes to proceed",
+            action,
+            count
+        );
+    }
+    let confirmed = Confirm::new()
+        .with_prompt(format!("This will {} {} pages. Continue?", action, count))
+        .default(false)
+        .interact()
+        .context("Failed to read confirmation")?;
+    if !confirmed {
+        println!("Aborted, no pages were written");
+    }
+    Ok(confirmed)
+}
+
+/// Handle the validate command
+///
+/// Reads every page and reports the issues found by each check. With
+/// `check_only`, fails with a summary of the issues if there are any.
+fn validate_command(
+    file_manager: &FileManager,
+    max_depth: u8,
+    quiet: bool,
+    check_only: bool,
+) -> Result<()> {
+    let titles = file_manager.list_pages()?;
+    let bar = progress::pages_bar(titles.len(), quiet);
+    let mut pages = Vec::new();
+    let mut issues = Vec::new();
+    for title in &titles {
+        bar.set_message(title.clone());
+        // Unreadable pages are reported like any other issue, so the rest
+        // of the vault is still checked
+        match file_manager.read_logseq_page(title) {
+            std::result::Result::Ok(page) => pages.push(page),
+            Err(e) => issues.push(validate::Issue {
+                page: title.clone(),
+                line: None,
+                message: format!("failed to read page: {:#}", e),
+            }),
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+
+    let aliases = AliasMap::new(&pages);
+    let block_ids = pages
+        .iter()
+        .flat_map(LogseqPage::block_ids)
+        .map(|id| id.to_lowercase())
+        .collect::<HashSet<_>>();
+    // Ignored pages still resolve links and block references to them
+    for page in pages
+        .iter()
+        .filter(|page| !file_manager.is_ignored_page(&page.title))
+    {
+        issues.extend(validate::check_max_depth(page, max_depth));
+        issues.extend(validate::check_indent_jumps(page));
+        issues.extend(validate::check_links(page, &aliases));
+        issues.extend(validate::check_block_refs(page, &block_ids));
+        issues.extend(validate::check_required_tags(
+            page,
+            file_manager.required_tags(),
+        ));
+        issues.extend(validate::check_version_title(page));
+        issues.extend(validate::check_version_headings(page));
+        issues.extend(validate::check_title_property(page));
+    }
+    issues.extend(
+        file_manager
+            .nested_page_files()?
+            .iter()
+            .map(|path| validate::check_nested_file(path)),
+    );
+
+    issues.iter().for_each(|issue| println!("{}", issue));
+    if !check_only {
+        println!("{} issues found", issues.len());
+        return Ok(());
+    }
+
+    let failed_pages = issues
+        .iter()
+        .map(|issue| &issue.page)
+        .collect::<HashSet<_>>()
+        .len();
+    if !issues.is_empty() {
+        bail!("{} errors across {} pages", issues.len(), failed_pages);
+    }
+    println!("0 errors across {} pages", titles.len());
+    Ok(())
+}
+
+/// Handle the export command
+///
+/// Writes the pages in canonical form to `output`. With `public_only`, pages
+/// without a `public:: true` property are skipped.
+fn export_command(
+    file_manager: &FileManager,
+    output: &Path,
+    public_only: bool,
+    format: ExportFormat,
+    strip_properties: bool,
+) -> Result<()> {
+    let mut exported = 0;
+    let mut skipped = 0;
+    for title in file_manager.list_pages()? {
+        if file_manager.is_ignored_page(&title) {
+            skipped += 1;
+            continue;
+        }
+        let page = file_manager.read_logseq_page(&title)?;
+        let is_public = page
+            .property("public")
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+        if public_only && !is_public {
+            skipped += 1;
+            continue;
+        }
+        let path = page
+            .title_to_path(output)
+            .with_extension(format.extension());
+        let rendered = render_page(file_manager, &page, format, strip_properties)?;
+        file_manager.writer().write(&path, rendered.as_bytes())?;
+        exported += 1;
+    }
+
+    println!(
+        "Exported {} pages to {}, skipped {}",
+        exported,
+        output.display(),
+        skipped
+    );
+    Ok(())
+}
+
+/// Print a single page in the export format
+fn export_page_command(
+    file_manager: &FileManager,
+    title: &str,
+    format: ExportFormat,
+    strip_properties: bool,
+) -> Result<()> {
+    let title = file_manager.resolve_page(title)?;
+    let page = file_manager.read_logseq_page(&title)?;
+    print!(
+        "{}",
+        render_page(file_manager, &page, format, strip_properties)?
+    );
+    Ok(())
+}
+
+/// Render a page in an export format
+///
+/// With `strip_properties`, the page is rendered without its properties.
+fn render_page(
+    file_manager: &FileManager,
+    page: &LogseqPage,
+    format: ExportFormat,
+    strip_properties: bool,
+) -> Result<String> {
+    let stripped;
+    let page = if strip_properties {
+        stripped = LogseqPage::new(&page.title, Vec::new(), page.contents.clone());
+        &stripped
+    } else {
+        page
+    };
+    Ok(match format {
+        ExportFormat::Logseq => file_manager.format_logseq_page(page),
+        ExportFormat::Json => serde_json::to_string_pretty(page)? + "\n",
+        ExportFormat::Markdown => export::to_markdown(&file_manager.prepare_write(page)),
+        ExportFormat::Org => export::to_org(&file_manager.prepare_write(page)),
+    })
+}
+
+/// Handle the check-roundtrip command
+///
+/// Compares each page file with what reading and rewriting it would produce,
+/// printing a diff for every page that differs. Fails if any page differs.
+fn check_roundtrip_command(file_manager: &FileManager, quiet: bool) -> Result<()> {
+    let titles = file_manager.list_pages()?;
+    let bar = progress::pages_bar(titles.len(), quiet);
+    let mut mismatches = 0;
+    for title in &titles {
+        bar.set_message(title.clone());
+        let current = read_page_file(file_manager, title)?;
+        let rewritten = file_manager.format_logseq_page(&file_manager.read_logseq_page(title)?);
+        if current != rewritten {
+            mismatches += 1;
+            let repo_path = file_manager.page_repo_path(title);
+            let diff = TextDiff::from_lines(&current, &rewritten)
+                .unified_diff()
+                .header(&repo_path, &format!("{} (rewritten)", repo_path))
+                .to_string();
+            bar.suspend(|| print!("{}", diff));
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+
+    if mismatches > 0 {
+        bail!("{} of {} pages don't roundtrip", mismatches, titles.len());
+    }
+    println!("All {} pages roundtrip", titles.len());
+    Ok(())
+}
+
+/// Read the file of a page as it is on disk
+fn read_page_file(file_manager: &FileManager, title: &str) -> Result<String> {
+    let path = LogseqPage::new(title, vec![], vec![]).title_to_path(&file_manager.pages_dir());
+    fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Handle the fmt command
+///
+/// Rewrites the pages that aren't in canonical form, warning about
+/// over-nested blocks
+fn fmt_command(
+    file_manager: &FileManager,
+    max_depth: u8,
+    fix_indent: bool,
+    compact_blanks: bool,
+    prune: bool,
+    quiet: bool,
+    yes: bool,
+) -> Result<()> {
+    let pages = format_pages(
+        file_manager,
+        max_depth,
+        fix_indent,
+        compact_blanks,
+        prune,
+        quiet,
+    )?;
+    if !confirm_writes(yes, "reformat", pages.len())? {
+        return Ok(());
+    }
+    for page in &pages {
+        file_manager.write_logseq_page(page)?;
+    }
+    println!("Formatted {} pages", pages.len());
+    Ok(())
+}
+
+/// Format every page, without writing anything
+///
+/// Returns the pages whose files would change, as compared by
+/// `check-roundtrip`.
+fn format_pages(
+    file_manager: &FileManager,
+    max_depth: u8,
+    fix_indent: bool,
+    compact_blanks: bool,
+    prune: bool,
+    quiet: bool,
+) -> Result<Vec<LogseqPage>> {
+    let headings = file_manager.changelog_headings();
+    let titles = file_manager.list_pages()?;
+    let bar = progress::pages_bar(titles.len(), quiet);
+    let mut changed = Vec::new();
+    for title in &titles {
+        bar.set_message(title.clone());
+        let mut page = file_manager.read_logseq_page(title)?;
+        if fix_indent {
+            let fixed = validate::fix_indent_jumps(&mut page);
+            if fixed > 0 {
+                bar.suspend(|| println!("{}: outdented {} blocks", title, fixed));
+            }
+        }
+        if compact_blanks {
+            let removed = validate::compact_blanks(&mut page);
+            if removed > 0 {
+                bar.suspend(|| println!("{}: removed {} blanks", title, removed));
+            }
+        }
+        let is_version_page =
+            page.has_tag("Version") || changelog::overflow_page_version(title).is_some();
+        if prune && is_version_page {
+            let pruned;
+            (page, pruned) = changelog::prune_empty_sections(&page, &headings);
+            if !pruned.is_empty() {
+                bar.suspend(|| println!("{}: pruned {}", title, pruned));
+            }
+        }
+        validate::check_max_depth(&page, max_depth)
+            .iter()
+            .for_each(|issue| bar.suspend(|| eprintln!("Warning: {}", issue)));
+        if file_manager.format_logseq_page(&page) != read_page_file(file_manager, title)? {
+            changed.push(page);
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+    Ok(changed)
+}
+
+/// Turn off colored output if requested
+///
+/// Without `no_color`, the output is colored only when it goes to a terminal
+/// and `NO_COLOR` isn't set, which `colored` and the prompts detect on their
+/// own.
+fn configure_color(no_color: bool) {
+    if no_color {
+        colored::control::set_override(false);
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+/// Main entry point for the SVLMD CLI tool
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    let root = file_manager::detect_root()?;
+    configure_color(cli.no_color);
+    // The self-test builds its own vault, so it doesn't need one here
+    if cli.command == Commands::SelfTest {
+        return self_test_command();
+    }
+    let root = file_manager::resolve_root(cli.root.as_deref())?;
 
-    if cli.command == Commands::Init {
+    if let Commands::Init(args) = &cli.command {
+        if cli.dry_run {
+            bail!("init can't be run with --dry-run");
+        }
         init_config(&root)?;
-        init(&root)?;
+        let file_manager = init(&root, false)?;
+        if let Some(version) = &args.seed_version {
+            seed_version(&file_manager, version, args.force)?;
+        }
+        if args.init_git {
+            init_git(&file_manager)?;
+        }
         return Ok(());
     }
 
-    let file_manager = init(&root)?;
+    if cli.command == Commands::Reinit {
+        if cli.dry_run {
+            bail!("reinit can't be run with --dry-run");
+        }
+        return reinit_command(&root);
+    }
+
+    let file_manager = init(&root, cli.dry_run)?;
+    // Nothing is written in a dry run, so there is nothing to confirm
+    let yes = cli.yes || cli.dry_run;
 
     // Handle commands
     match cli.command {
-        Commands::Init => unreachable!(),
-        Commands::Sync { version, verbose } => sync_command(&file_manager, version, verbose),
+        Commands::Init(_) | Commands::Reinit | Commands::SelfTest => unreachable!(),
+        Commands::Sync(args) => sync_command(&file_manager, &args),
+        Commands::Release(args) => release_command(&file_manager, &args),
+        Commands::Status(args) => status_command(&file_manager, &args),
+        Commands::Rebuild => rebuild_command(&file_manager),
+        Commands::Watch { sync, debounce } => {
+            watch_command(&file_manager, sync, Duration::from_millis(debounce))
+        }
+        Commands::New { title, template } => {
+            new_command(&file_manager, &title, template.as_deref())
+        }
+        Commands::Backlinks { title } => backlinks_command(&file_manager, &title),
+        Commands::Hubs { top, json } => hubs_command(&file_manager, top, json),
+        Commands::Rename { from, to } => {
+            rename_command(&file_manager, &from, &to, cli.dry_run, yes)
+        }
+        Commands::Import { dir, overwrite } => import_command(&file_manager, &dir, overwrite, yes),
+        Commands::RenameMap { file } => rename_map_command(&file_manager, &file, cli.dry_run, yes),
+        Commands::Alias {
+            page,
+            action,
+            value,
+        } => alias_command(&file_manager, &page, action, &value),
+        Commands::Retag {
+            tag,
+            title_glob,
+            content_contains,
+        } => retag_command(
+            &file_manager,
+            &tag,
+            title_glob.as_deref(),
+            content_contains.as_deref(),
+            cli.dry_run,
+            yes,
+        ),
+        Commands::Stats { format, json } => stats_command(&file_manager, format, json),
+        Commands::Doctor { json, fix } => doctor_command(&file_manager, json, fix),
+        Commands::List(args) => list_command(&file_manager, &args),
+        Commands::Search {
+            query,
+            pagination,
+            json,
+            json_lines,
+        } => search_command(&file_manager, &query, &pagination, json, json_lines),
+        Commands::DedupeChangelog { title } => {
+            dedupe_changelog_command(&file_manager, title.as_deref(), yes)
+        }
+        Commands::Unrecorded { sort, json } => unrecorded_command(&file_manager, sort, json),
+        Commands::Unstaged { strict } => unstaged_command(&file_manager, strict),
+        Commands::Validate {
+            max_depth,
+            quiet,
+            check_only,
+        } => validate_command(&file_manager, max_depth, quiet, check_only),
+        Commands::Export {
+            title,
+            output,
+            public_only,
+            format,
+            strip_properties,
+        } => match (title, output) {
+            (Some(title), _) => {
+                export_page_command(&file_manager, &title, format, strip_properties)
+            }
+            (None, Some(output)) => export_command(
+                &file_manager,
+                &output,
+                public_only,
+                format,
+                strip_properties,
+            ),
+            (None, None) => unreachable!("clap requires --output without a title"),
+        },
+        Commands::CheckRoundtrip { quiet } => check_roundtrip_command(&file_manager, quiet),
+        Commands::Fmt {
+            max_depth,
+            fix_indent,
+            compact_blanks,
+            prune,
+            quiet,
+        } => fmt_command(
+            &file_manager,
+            max_depth,
+            fix_indent,
+            compact_blanks,
+            prune,
+            quiet,
+            yes,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::tests::{add_conflict, init_git, vault, write_page_file};
+
+    #[test]
+    fn new_page_differing_only_in_case_keeps_the_existing_page() {
+        let (_dir, file_manager) = vault(serde_json::json!({ "case_insensitive_pages": true }));
+        write_page_file(&file_manager, "drug", "- original\n");
+
+        let error = new_command(&file_manager, "Drug", None).unwrap_err();
+        assert!(error.to_string().ends_with("already exists"));
+        let page = file_manager.read_logseq_page("drug").unwrap();
+        assert_eq!(page.contents, [("original".to_string(), 0)]);
+        assert_eq!(file_manager.list_pages().unwrap(), ["drug"]);
+    }
+
+    #[test]
+    fn stats_skip_blank_lines_and_empty_bullets() {
+        let tagged = LogseqPage::from_plain(
+            "Aspirin",
+            vec![("tags".into(), "Drug, [[NSAID]]".into())],
+            "- [[Pain]] relief\n-\n\n    - dose\n    -",
+        );
+        let untagged = LogseqPage::from_plain("Note", vec![], "-\n- [[Aspirin]]");
+        let lowercase = LogseqPage::new("Ibuprofen", vec![("tags".into(), "drug".into())], vec![]);
+
+        let stats = collect_stats(&[tagged, untagged, lowercase]);
+        assert_eq!(stats.pages, 3);
+        assert_eq!(stats.blocks, 3);
+        assert_eq!(stats.links, 2);
+        assert_eq!(stats.untagged, 1);
+        let tags = stats
+            .tags
+            .iter()
+            .map(|t| (t.tag.as_str(), t.count))
+            .collect::<Vec<_>>();
+        assert_eq!(tags, [("Drug", 2), ("NSAID", 1)]);
+    }
+
+    #[test]
+    fn contributor_name_is_trimmed() {
+        assert_eq!(contributor_name("  Sira P.\t ").as_deref(), Some("Sira P."));
+        assert_eq!(contributor_name(" \t "), None);
+    }
+
+    /// Parse the arguments of a sync command line
+    fn sync_args(args: &[&str]) -> SyncArgs {
+        let command = ["svlmd", "sync"].iter().chain(args);
+        match Cli::try_parse_from(command).unwrap().command {
+            Commands::Sync(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn fmt_rewrites_only_pages_that_change() {
+        let (_dir, file_manager) = vault(serde_json::json!({}));
+        write_page_file(&file_manager, "Aspirin", "tags::Drug\n\n- Dosage\n");
+        write_page_file(&file_manager, "Metformin", "- Dosage\n\n\n- Side effects\n");
+        let formatted = LogseqPage::new("Zinc", vec![], vec![("Dosage".to_string(), 0)]);
+        file_manager.write_logseq_page(&formatted).unwrap();
+
+        let titles =
+            |pages: Vec<LogseqPage>| pages.into_iter().map(|page| page.title).collect::<Vec<_>>();
+        let changed = format_pages(&file_manager, 6, false, false, false, true).unwrap();
+        assert_eq!(titles(changed), ["Aspirin"]);
+        let changed = format_pages(&file_manager, 6, false, true, false, true).unwrap();
+        assert_eq!(titles(changed), ["Aspirin", "Metformin"]);
+
+        fmt_command(&file_manager, 6, false, true, false, true, true).unwrap();
+        assert!(format_pages(&file_manager, 6, false, true, false, true)
+            .unwrap()
+            .is_empty());
+        // With nothing to write, there is nothing to confirm
+        fmt_command(&file_manager, 6, false, true, false, true, false).unwrap();
+    }
+
+    #[test]
+    fn init_keeps_or_refuses_an_existing_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".svlmd");
+        assert!(existing_config(&path).unwrap().contributor.is_empty());
+
+        fs::write(&path, "  \n").unwrap();
+        assert!(existing_config(&path).unwrap().contributor.is_empty());
+
+        fs::write(
+            &path,
+            r#"{ "contributor": "Tester", "pages_dir": "notes" }"#,
+        )
+        .unwrap();
+        let config = existing_config(&path).unwrap();
+        assert_eq!(config.contributor, "Tester");
+        assert_eq!(config.pages_dir.as_deref(), Some("notes"));
+
+        let text = r#"{ "contributor": "Tester", "pages_dri": "notes" }"#;
+        fs::write(&path, text).unwrap();
+        let error = existing_config(&path).unwrap_err();
+        assert!(format!("{:#}", error).starts_with("Fix or remove .svlmd"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+    }
+
+    #[test]
+    fn target_version_is_parsed_apart_from_version() {
+        let args = sync_args(&["--target-version", "1.2.3-rc.1", "-V"]);
+        assert_eq!(
+            args.target_version,
+            Some(semver::Version::parse("1.2.3-rc.1").unwrap())
+        );
+        assert!(args.version);
+
+        assert!(Cli::try_parse_from(["svlmd", "sync", "--target-version", "1.2"]).is_err());
+    }
+
+    #[test]
+    fn strict_sync_refuses_conflicted_pages() {
+        let (dir, file_manager) = vault(serde_json::json!({ "email": "tester@example.com" }));
+        let repo = init_git(&file_manager);
+        fs::write(dir.path().join("version.txt"), "1.0.0\n").unwrap();
+        write_page_file(&file_manager, "Aspirin", "- base\n");
+        file_manager
+            .commit(&["pages/Aspirin.md".to_string()], "Add Aspirin")
+            .unwrap();
+        add_conflict(&repo, "pages/Aspirin.md");
+
+        let error = sync_command(&file_manager, &sync_args(&["--strict"])).unwrap_err();
+        assert_eq!(error.to_string(), "1 pages have merge conflicts: Aspirin");
+        assert!(!file_manager.logseq_page_exists("1.0.0"));
+
+        // Without --strict the conflicted page is left out
+        sync_command(&file_manager, &sync_args(&[])).unwrap();
+        let page = file_manager.read_logseq_page("1.0.0").unwrap();
+        let recorded = changelog::recorded_pages(&page, &file_manager.changelog_headings());
+        assert!(recorded.is_empty());
     }
 }
@@ -0,0 +1,17 @@
+/// Progress reporting for long-running scans
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::{stderr, IsTerminal};
+
+/// Create a progress bar for processing `len` pages
+///
+/// The bar draws to stderr and is hidden when `quiet` is set or stderr
+/// isn't a terminal.
+pub fn pages_bar(len: usize, quiet: bool) -> ProgressBar {
+    if quiet || !stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(len as u64).with_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} {wide_msg}")
+            .expect("progress template is valid"),
+    )
+}
@@ -0,0 +1,194 @@
+/// Rename map files for batch renames
+///
+/// A rename map lists pages to rename and their new titles, either as CSV
+/// with one `old,new` pair per line or, for files ending in `.json`, as a JSON
+/// object mapping old titles to new ones. In CSV, blank lines and lines
+/// starting with `#` are skipped, an `old,new` header line is allowed, and
+/// titles containing commas can be quoted with `"`, doubling quotes inside.
+use anyhow::{bail, Context, Result};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// A rename listed in a rename map
+#[derive(Debug)]
+pub struct Entry {
+    /// Title of the page to rename
+    pub from: String,
+    /// New title of the page
+    pub to: String,
+    /// Where the entry is in the file, e.g. `line 3`, for error messages
+    pub location: String,
+}
+
+/// Read a rename map, choosing the format from the file extension
+pub fn load(path: &Path) -> Result<Vec<Entry>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read rename map {}", path.display()))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let entries = if is_json {
+        parse_json(&text)
+    } else {
+        parse_csv(&text)
+    };
+    entries.with_context(|| format!("Invalid rename map {}", path.display()))
+}
+
+/// Parse a JSON object mapping old titles to new ones
+///
+/// Entries are returned sorted by old title.
+pub fn parse_json(text: &str) -> Result<Vec<Entry>> {
+    let map: BTreeMap<String, String> =
+        serde_json::from_str(text).context("Expected a JSON object of old and new titles")?;
+    Ok(map
+        .into_iter()
+        .map(|(from, to)| Entry {
+            location: format!("key '{}'", from),
+            from: from.trim().to_string(),
+            to: to.trim().to_string(),
+        })
+        .collect())
+}
+
+/// Parse `old,new` lines
+pub fn parse_csv(text: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = parse_csv_fields(line).with_context(|| format!("On line {}", i + 1))?;
+        let [from, to] = fields.as_slice() else {
+            bail!(
+                "Expected 2 fields on line {}, found {}",
+                i + 1,
+                fields.len()
+            );
+        };
+        if entries.is_empty() && from == "old" && to == "new" {
+            continue;
+        }
+        entries.push(Entry {
+            from: from.clone(),
+            to: to.clone(),
+            location: format!("line {}", i + 1),
+        });
+    }
+    Ok(entries)
+}
+
+/// Split a CSV line into trimmed fields, unquoting quoted fields
+fn parse_csv_fields(line: &str) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        bail!("Unclosed quote");
+    }
+    fields.push(field.trim().to_string());
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the titles of entries as `(from, to)` pairs
+    fn pairs(entries: &[Entry]) -> Vec<(&str, &str)> {
+        entries
+            .iter()
+            .map(|entry| (entry.from.as_str(), entry.to.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn csv_skips_comments_blank_lines_and_the_header() {
+        let text = "# Drug renames\nold,new\n\nAspirin, Drug/Aspirin\n  Zinc,Mineral/Zinc  \n";
+        let entries = parse_csv(text).unwrap();
+        assert_eq!(
+            pairs(&entries),
+            [("Aspirin", "Drug/Aspirin"), ("Zinc", "Mineral/Zinc")]
+        );
+        assert_eq!(entries[0].location, "line 4");
+        assert_eq!(entries[1].location, "line 5");
+
+        // Only a first line can be the header
+        let entries = parse_csv("Aspirin,ASA\nold,new\n").unwrap();
+        assert_eq!(pairs(&entries), [("Aspirin", "ASA"), ("old", "new")]);
+    }
+
+    #[test]
+    fn csv_quotes_hold_commas_and_doubled_quotes() {
+        let text = r#""Aspirin, 500 mg","Drug/Aspirin ""Bayer"""
+"",Empty"#;
+        let entries = parse_csv(text).unwrap();
+        assert_eq!(
+            pairs(&entries),
+            [
+                ("Aspirin, 500 mg", r#"Drug/Aspirin "Bayer""#),
+                ("", "Empty")
+            ]
+        );
+        // A quote inside an unquoted field is kept as text
+        let entries = parse_csv(r#"5" nail,Nail"#).unwrap();
+        assert_eq!(pairs(&entries), [(r#"5" nail"#, "Nail")]);
+    }
+
+    #[test]
+    fn csv_errors_name_the_line() {
+        let error = parse_csv("Aspirin,ASA\n\"Zinc,Mineral\n").unwrap_err();
+        assert_eq!(format!("{:#}", error), "On line 2: Unclosed quote");
+
+        let error = parse_csv("Aspirin\n").unwrap_err();
+        assert_eq!(error.to_string(), "Expected 2 fields on line 1, found 1");
+
+        let error = parse_csv("# renames\nAspirin,ASA,Drug\n").unwrap_err();
+        assert_eq!(error.to_string(), "Expected 2 fields on line 2, found 3");
+    }
+
+    #[test]
+    fn json_maps_old_titles_to_new_ones() {
+        let entries = parse_json(r#"{ "Zinc": "Mineral/Zinc", " Aspirin ": "ASA " }"#).unwrap();
+        assert_eq!(
+            pairs(&entries),
+            [("Aspirin", "ASA"), ("Zinc", "Mineral/Zinc")]
+        );
+        assert_eq!(entries[0].location, "key ' Aspirin '");
+
+        let error = parse_json(r#"["Aspirin", "ASA"]"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected a JSON object of old and new titles"
+        );
+    }
+
+    #[test]
+    fn format_follows_the_file_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("renames.JSON");
+        fs::write(&json, r#"{ "Aspirin": "ASA" }"#).unwrap();
+        assert_eq!(pairs(&load(&json).unwrap()), [("Aspirin", "ASA")]);
+
+        let csv = dir.path().join("renames.csv");
+        fs::write(&csv, r#"{ "Aspirin": "ASA" }"#).unwrap();
+        let error = load(&csv).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid rename map"));
+    }
+}
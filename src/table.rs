@@ -0,0 +1,163 @@
+/// Aligned table output
+///
+/// Columns are separated by two spaces and padded to their widest cell. When
+/// stdout is a terminal too narrow for the table, the first column is
+/// truncated with an ellipsis so the other columns stay aligned.
+use console::Term;
+
+/// Separator between columns
+const SEPARATOR: &str = "  ";
+
+/// Narrowest the first column is truncated to
+const MIN_FIRST_WIDTH: usize = 8;
+
+/// A table of text cells with a header row
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Create an empty table with the given column headers
+    pub fn new(headers: &[&'static str]) -> Self {
+        Self {
+            headers: headers.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Append a row, with one cell per column
+    pub fn push(&mut self, row: Vec<String>) {
+        debug_assert_eq!(row.len(), self.headers.len());
+        self.rows.push(row);
+    }
+
+    /// Render the table, fitting it into `max_width` columns if given
+    pub fn render(&self, max_width: Option<usize>) -> String {
+        let mut widths = self
+            .headers
+            .iter()
+            .map(|header| header.chars().count())
+            .collect::<Vec<_>>();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        if let Some(max_width) = max_width {
+            let others = widths[1..].iter().sum::<usize>() + SEPARATOR.len() * (widths.len() - 1);
+            let available = max_width.saturating_sub(others).max(MIN_FIRST_WIDTH);
+            widths[0] = widths[0].min(available);
+        }
+
+        let headers = self.headers.iter().map(|header| header.to_string());
+        std::iter::once(headers.collect::<Vec<_>>())
+            .chain(self.rows.iter().cloned())
+            .map(|row| render_row(&row, &widths) + "\n")
+            .collect()
+    }
+
+    /// Print the table to stdout, fitted to the terminal width
+    pub fn print(&self) {
+        let width = Term::stdout()
+            .size_checked()
+            .map(|(_, columns)| columns as usize);
+        print!("{}", self.render(width));
+    }
+}
+
+/// Render a row padded to the column widths, without trailing spaces
+fn render_row(row: &[String], widths: &[usize]) -> String {
+    let line = row
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:width$}", truncate(cell, *width), width = width))
+        .collect::<Vec<_>>()
+        .join(SEPARATOR);
+    line.trim_end().to_string()
+}
+
+/// Shorten a cell to `width` characters, ending it with an ellipsis if cut
+fn truncate(cell: &str, width: usize) -> String {
+    if cell.chars().count() <= width {
+        return cell.to_string();
+    }
+    let mut truncated = cell
+        .chars()
+        .take(width.saturating_sub(1))
+        .collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a table of pages with a long title
+    fn pages() -> Table {
+        let mut table = Table::new(&["Title", "Tags", "Modified"]);
+        table.push(vec![
+            "Acetylsalicylic acid".to_string(),
+            "Drug".to_string(),
+            "2026-10-01".to_string(),
+        ]);
+        table.push(vec![
+            "Zinc".to_string(),
+            String::new(),
+            "2026-09-30".to_string(),
+        ]);
+        table
+    }
+
+    #[test]
+    fn columns_are_padded_without_trailing_spaces() {
+        assert_eq!(
+            pages().render(None),
+            "Title                 Tags  Modified\n\
+             Acetylsalicylic acid  Drug  2026-10-01\n\
+             Zinc                        2026-09-30\n"
+        );
+
+        let mut table = Table::new(&["Title", "Tags"]);
+        table.push(vec!["Aspirin".to_string(), String::new()]);
+        assert_eq!(table.render(None), "Title    Tags\nAspirin\n");
+    }
+
+    #[test]
+    fn narrow_tables_truncate_the_first_column() {
+        assert_eq!(
+            pages().render(Some(30)),
+            "Title         Tags  Modified\n\
+             Acetylsalic…  Drug  2026-10-01\n\
+             Zinc                2026-09-30\n"
+        );
+    }
+
+    #[test]
+    fn first_column_keeps_a_minimum_width() {
+        let rendered = pages().render(Some(10));
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], "Acetyls…  Drug  2026-10-01");
+        assert_eq!(lines[2], "Zinc            2026-09-30");
+    }
+
+    #[test]
+    fn widths_are_counted_in_characters() {
+        let mut table = Table::new(&["Title", "Tags"]);
+        table.push(vec!["Äpfelsäure-Öl".to_string(), "Säure".to_string()]);
+        table.push(vec!["Zink".to_string(), "Mineral".to_string()]);
+        assert_eq!(
+            table.render(None),
+            "Title          Tags\n\
+             Äpfelsäure-Öl  Säure\n\
+             Zink           Mineral\n"
+        );
+        assert_eq!(
+            table.render(Some(20)),
+            "Title        Tags\n\
+             Äpfelsäure…  Säure\n\
+             Zink         Mineral\n"
+        );
+    }
+}
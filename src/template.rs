@@ -0,0 +1,70 @@
+/// Page templates
+///
+/// Templates are Logseq pages stored under `templates/` at the project root.
+/// Property values and content lines may contain placeholder tokens which are
+/// substituted when the template is instantiated:
+/// - `{{title}}`: the title of the new page
+/// - `{{date}}`: the current date
+use anyhow::{bail, Result};
+
+use crate::config::ChangelogHeadings;
+use crate::file_manager::{FileManager, LogseqPage};
+
+/// Name of the built-in template used for version pages
+pub const VERSION_TEMPLATE: &str = "version";
+
+/// Load a template by name
+///
+/// Looks for `templates/<name>.md` first and falls back to the built-in
+/// templates. Fails if neither exists.
+pub fn load(file_manager: &FileManager, name: &str) -> Result<LogseqPage> {
+    let template = LogseqPage::new(name, vec![], vec![]);
+    let templates_dir = file_manager.resolve("templates")?;
+    if template.title_to_path(&templates_dir).exists() {
+        return template.read_page(&templates_dir);
+    }
+
+    match builtin(name, &file_manager.changelog_headings()) {
+        Some(page) => Ok(page),
+        None => bail!("Template '{}' not found in templates/", name),
+    }
+}
+
+/// Create a page from a template, substituting the placeholder tokens
+pub fn instantiate(template: &LogseqPage, title: &str, date: &str) -> LogseqPage {
+    let substitute = |text: &str| text.replace("{{title}}", title).replace("{{date}}", date);
+    LogseqPage::new(
+        title,
+        template
+            .properties
+            .iter()
+            .map(|(key, value)| (key.clone(), substitute(value)))
+            .collect(),
+        template
+            .contents
+            .iter()
+            .map(|(line, indent)| (substitute(line), *indent))
+            .collect(),
+    )
+}
+
+/// Get a built-in template by name
+///
+/// The version template is laid out with the configured `headings`.
+fn builtin(name: &str, headings: &ChangelogHeadings) -> Option<LogseqPage> {
+    match name {
+        VERSION_TEMPLATE => Some(LogseqPage::new(
+            VERSION_TEMPLATE,
+            vec![
+                ("tags".into(), "Version".into()),
+                ("released-date".into(), "{{date}}".into()),
+            ],
+            vec![
+                (headings.summary.clone(), 0),
+                ("".into(), 0),
+                (headings.changed_pages.clone(), 0),
+            ],
+        )),
+        _ => None,
+    }
+}
@@ -0,0 +1,342 @@
+/// Page validation checks
+///
+/// Each check inspects a parsed Logseq page and reports the problems it finds
+/// as issues pointing at the page and, where possible, the offending line.
+use std::{collections::HashSet, fmt};
+
+use crate::changelog;
+use crate::file_manager::{self, LogseqPage};
+use crate::links::{self, AliasMap};
+
+/// Default maximum indentation depth allowed by the style guide
+pub const DEFAULT_MAX_DEPTH: u8 = 3;
+
+/// A problem found while validating a page
+pub struct Issue {
+    /// Title of the page containing the problem
+    pub page: String,
+    /// 1-based line of the problem, if it can be located
+    pub line: Option<usize>,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.page, line, self.message),
+            None => write!(f, "{}: {}", self.page, self.message),
+        }
+    }
+}
+
+/// Check that no content block is nested deeper than `max_depth`
+pub fn check_max_depth(page: &LogseqPage, max_depth: u8) -> Vec<Issue> {
+    page.contents
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, indent))| *indent > max_depth)
+        .map(|(i, (_, indent))| Issue {
+            page: page.title.clone(),
+            line: Some(page.line_number(i)),
+            message: format!("block nested {} levels deep (max {})", indent, max_depth),
+        })
+        .collect()
+}
+
+/// Check that no block is indented more than one level below the block
+/// before it
+///
+/// Blank lines are skipped. The first block must not be indented at all.
+pub fn check_indent_jumps(page: &LogseqPage) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut previous = None;
+    for (i, (line, indent)) in page.contents.iter().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let allowed = previous.map_or(0, |previous: u8| previous.saturating_add(1));
+        if *indent > allowed {
+            issues.push(Issue {
+                page: page.title.clone(),
+                line: Some(page.line_number(i)),
+                message: format!(
+                    "block indented {} levels, more than one level below the block before it",
+                    indent
+                ),
+            });
+        }
+        previous = Some(*indent);
+    }
+    issues
+}
+
+/// Outdent blocks that are indented more than one level below their parent
+///
+/// Each block is placed one level below the nearest preceding block that was
+/// less indented, so children of an outdented block move with it and
+/// siblings stay siblings. Returns the number of blocks moved.
+pub fn fix_indent_jumps(page: &mut LogseqPage) -> usize {
+    // Original and fixed indentation of the blocks enclosing the current one
+    let mut ancestors: Vec<(u8, u8)> = Vec::new();
+    let mut fixed = 0;
+    for (line, indent) in page.contents.iter_mut() {
+        if line.is_empty() {
+            continue;
+        }
+        while ancestors
+            .last()
+            .is_some_and(|(original, _)| *original >= *indent)
+        {
+            ancestors.pop();
+        }
+        let target = ancestors.last().map_or(0, |(_, parent)| parent + 1);
+        ancestors.push((*indent, target));
+        if target != *indent {
+            *indent = target;
+            fixed += 1;
+        }
+    }
+    fixed
+}
+
+/// Collapse runs of consecutive blank lines and empty blocks into their
+/// first entry
+///
+/// Blank lines inside fenced code blocks are kept, as they are part of the
+/// code. Returns the number of entries removed.
+pub fn compact_blanks(page: &mut LogseqPage) -> usize {
+    let before = page.contents.len();
+    let mut in_code = false;
+    let mut previous_blank = false;
+    page.contents.retain(|(line, _)| {
+        // A fence opens or closes a code block, unless it closes on the same line
+        if file_manager::block_text(line).matches("```").count() % 2 == 1 {
+            in_code = !in_code;
+        }
+        let blank = !in_code && file_manager::is_blank(line);
+        let keep = !(blank && previous_blank);
+        previous_blank = blank;
+        keep
+    });
+    before - page.contents.len()
+}
+
+/// Check that every link points at an existing page or alias
+///
+/// Version pages and their overflow pages are skipped, as their changelogs
+/// link to deleted pages.
+pub fn check_links(page: &LogseqPage, aliases: &AliasMap) -> Vec<Issue> {
+    if page.has_tag("Version") || changelog::overflow_page_version(&page.title).is_some() {
+        return vec![];
+    }
+    page.contents
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (line, _))| {
+            links::extract_links(line)
+                .into_iter()
+                .filter(|target| aliases.resolve(target).is_none())
+                .map(move |target| Issue {
+                    page: page.title.clone(),
+                    line: Some(page.line_number(i)),
+                    message: format!("broken link [[{}]]", target),
+                })
+        })
+        .collect()
+}
+
+/// Check that every block reference points at a block ID declared in the vault
+///
+/// `block_ids` holds the lowercase IDs of all blocks across the vault.
+pub fn check_block_refs(page: &LogseqPage, block_ids: &HashSet<String>) -> Vec<Issue> {
+    page.contents
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (line, _))| {
+            links::extract_block_refs(line)
+                .into_iter()
+                .filter(|uuid| !block_ids.contains(&uuid.to_lowercase()))
+                .map(move |uuid| Issue {
+                    page: page.title.clone(),
+                    line: Some(page.line_number(i)),
+                    message: format!("dangling block reference (({}))", uuid),
+                })
+        })
+        .collect()
+}
+
+/// Check that the page carries at least one tag from each required set
+///
+/// Author and Version pages are exempt, as are the Author and Version tag
+/// pages themselves.
+pub fn check_required_tags(page: &LogseqPage, required_tags: &[Vec<String>]) -> Vec<Issue> {
+    let exempt = ["Author", "Version"]
+        .iter()
+        .any(|tag| page.has_tag(tag) || page.title == *tag);
+    if exempt {
+        return vec![];
+    }
+    required_tags
+        .iter()
+        .filter(|set| !set.iter().any(|tag| page.has_tag(tag)))
+        .map(|set| Issue {
+            page: page.title.clone(),
+            line: None,
+            message: format!("missing a required tag, one of: {}", set.join(", ")),
+        })
+        .collect()
+}
+
+/// Check that a page's `title::` property matches the title derived from its
+/// filename
+///
+/// A file renamed by hand keeps its old `title::`, which Logseq then shows
+/// instead of the title that links and svlmd use.
+pub fn check_title_property(page: &LogseqPage) -> Vec<Issue> {
+    let Some(title) = page.property("title") else {
+        return vec![];
+    };
+    if title == page.title {
+        return vec![];
+    }
+    vec![Issue {
+        page: page.title.clone(),
+        line: None,
+        message: format!(
+            "title property '{}' doesn't match the title '{}' of the file {}.md",
+            title,
+            page.title,
+            page.title.replace("/", "___")
+        ),
+    }]
+}
+
+/// Report a page file in a subdirectory of the pages directory
+///
+/// `path` is relative to the pages directory. Such files are never read as
+/// pages, as `/` in titles is written as `___` in filenames.
+pub fn check_nested_file(path: &str) -> Issue {
+    let title = path.strip_suffix(".md").unwrap_or(path);
+    Issue {
+        page: title.to_string(),
+        line: None,
+        message: format!(
+            "file {} is in a subdirectory and isn't read as a page; rename it to {}.md",
+            path,
+            title.replace("/", "___")
+        ),
+    }
+}
+
+/// Check that a Version-tagged page is titled with a semantic version
+///
+/// Titles such as `1.2` or `v1.2.3` don't match the pages `sync` looks up, so
+/// the next sync silently creates a duplicate page.
+pub fn check_version_title(page: &LogseqPage) -> Vec<Issue> {
+    if !page.has_tag("Version") || semver::Version::parse(&page.title).is_ok() {
+        return vec![];
+    }
+    vec![Issue {
+        page: page.title.clone(),
+        line: None,
+        message: format!(
+            "version page title '{}' is not a semantic version",
+            page.title
+        ),
+    }]
+}
+
+/// Check that every version entry heading of a version page is a
+/// well-formed link to a semantic version, such as `## [[1.2.3]]`
+///
+/// Applies to Version-tagged pages and overflow pages. A hand-edited heading
+/// like `## [[1.2.3]` isn't recognized as an entry, so a sync would record
+/// its changes in the wrong place.
+pub fn check_version_headings(page: &LogseqPage) -> Vec<Issue> {
+    if !page.has_tag("Version") && changelog::overflow_page_version(&page.title).is_none() {
+        return vec![];
+    }
+    page.contents
+        .iter()
+        .enumerate()
+        .filter(|(_, (line, indent))| *indent == 1 && line.starts_with("## "))
+        .filter(|(_, (line, _))| {
+            let heading = file_manager::block_text(line);
+            let version = heading
+                .strip_prefix("## [[")
+                .and_then(|heading| heading.strip_suffix("]]"));
+            version.is_none_or(|version| semver::Version::parse(version).is_err())
+        })
+        .map(|(i, (line, _))| Issue {
+            page: page.title.clone(),
+            line: Some(page.line_number(i)),
+            message: format!(
+                "malformed version heading '{}', expected '## [[<version>]]'",
+                file_manager::block_text(line)
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a page from plain text contents, without properties
+    fn page(contents: &str) -> LogseqPage {
+        LogseqPage::from_plain("Page", vec![], contents)
+    }
+
+    #[test]
+    fn max_depth_allows_blocks_at_the_limit() {
+        let page = page("- a\n    - b\n        - c\n            - d");
+        assert!(check_max_depth(&page, 3).is_empty());
+    }
+
+    #[test]
+    fn max_depth_reports_blocks_beyond_the_limit() {
+        let page = page("- a\n    - b\n        - c\n            - d\n                - e");
+        let issues = check_max_depth(&page, 3);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].page, "Page");
+        assert_eq!(issues[0].line, Some(5));
+        assert_eq!(issues[0].message, "block nested 4 levels deep (max 3)");
+    }
+
+    #[test]
+    fn links_through_aliases_are_not_broken() {
+        let aspirin = LogseqPage::new(
+            "Aspirin",
+            vec![("alias".into(), "ASA, [[Acetylsalicylic acid]]".into())],
+            vec![],
+        );
+        let note = LogseqPage::from_plain(
+            "Note",
+            vec![],
+            "- [[asa]] and [[Acetylsalicylic Acid]]\n- [[Aspirin]] but not [[Paracetamol]]",
+        );
+        let aliases = AliasMap::new(&[aspirin, note.clone()]);
+
+        let issues = check_links(&note, &aliases);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(2));
+        assert_eq!(issues[0].message, "broken link [[Paracetamol]]");
+    }
+
+    #[test]
+    fn only_uuid_block_refs_can_dangle() {
+        let page = page(
+            "- f((x)) grows\n- ((6500a1b2-3c4d-4e5f-8a9b-0c1d2e3f4a5b))\n- ((6500A1B2-3C4D-4E5F-8A9B-000000000000))",
+        );
+        let block_ids = HashSet::from(["6500a1b2-3c4d-4e5f-8a9b-000000000000".to_string()]);
+
+        let issues = check_block_refs(&page, &block_ids);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(2));
+        assert_eq!(
+            issues[0].message,
+            "dangling block reference ((6500a1b2-3c4d-4e5f-8a9b-0c1d2e3f4a5b))"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+/// Current version lookup
+///
+/// The current version is read from the source configured in `.svlmd`: a
+/// plain text file, a key of a JSON file, or the latest `v*` git tag. Without
+/// a configured source, the first of [`VERSION_FILES`] at the project root
+/// that holds a semantic version is used.
+///
+/// In a text file, blank lines and surrounding whitespace are ignored. The
+/// first remaining line is the version, and the line after it, if any, is
+/// the default Summary of the version page. Further lines are ignored.
+use anyhow::{anyhow, bail, Context, Result};
+use semver::Version;
+
+use crate::config::VersionSource;
+use crate::file_manager::FileManager;
+
+/// Text files probed for the version, in priority order, when no version
+/// source is configured
+pub const VERSION_FILES: [&str; 3] = ["version.txt", "VERSION", ".version"];
+
+/// Read the current version from the configured source
+///
+/// Without a configured source, the error lists every file tried.
+pub fn read(file_manager: &FileManager) -> Result<Version> {
+    match file_manager.version_source() {
+        VersionSource::Text { path } if !file_manager.version_source_configured() => {
+            read_text(file_manager, &path).map_err(|_| {
+                let tried = VERSION_FILES
+                    .iter()
+                    .filter_map(|path| read_text(file_manager, path).err())
+                    .map(|e| format!("{:#}", e))
+                    .collect::<Vec<_>>();
+                anyhow!("No version file found, tried: {}", tried.join("; "))
+            })
+        }
+        VersionSource::Text { path } => read_text(file_manager, &path),
+        VersionSource::Json { path, key } => {
+            let text = read_file(file_manager, &path)?;
+            let json: serde_json::Value =
+                serde_json::from_str(&text).with_context(|| format!("Failed to parse {}", path))?;
+            let value = key
+                .split('.')
+                .try_fold(&json, |value, part| value.get(part))
+                .and_then(|value| value.as_str())
+                .ok_or_else(|| anyhow!("Key '{}' not found in {} or not a string", key, path))?;
+            Version::parse(value)
+                .with_context(|| format!("Failed to parse version at key '{}' in {}", key, path))
+        }
+        VersionSource::GitTags => file_manager
+            .version_tags()?
+            .pop()
+            .map(|tag| tag.version)
+            .ok_or_else(|| anyhow!("No v* version tags found in git")),
+    }
+}
+
+/// Read the version from the first line of a text file
+pub fn read_text(file_manager: &FileManager, path: &str) -> Result<Version> {
+    let text = read_file(file_manager, path)?;
+    let line = text_lines(&text)
+        .next()
+        .ok_or_else(|| anyhow!("{} has no version", path))?;
+    Version::parse(line).with_context(|| format!("Failed to parse version in {}", path))
+}
+
+/// Read the default Summary of the version page from the configured source
+///
+/// Only text sources provide a Summary.
+pub fn read_summary(file_manager: &FileManager) -> Result<Option<String>> {
+    match file_manager.version_source() {
+        VersionSource::Text { path } => {
+            let text = read_file(file_manager, &path)?;
+            let summary = text_lines(&text).nth(1).map(String::from);
+            Ok(summary)
+        }
+        VersionSource::Json { .. } | VersionSource::GitTags => Ok(None),
+    }
+}
+
+/// Iterate over the non-empty lines of a text file, trimmed
+fn text_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines().map(str::trim).filter(|line| !line.is_empty())
+}
+
+/// Read a version file relative to the project root
+fn read_file(file_manager: &FileManager, path: &str) -> Result<String> {
+    if !file_manager.resolve(path)?.exists() {
+        bail!("{} not found", path);
+    }
+    file_manager.read_to_string(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_manager::tests::vault;
+    use std::fs;
+
+    #[test]
+    fn version_file_with_summary_and_notes() {
+        let (dir, file_manager) = vault(serde_json::json!({}));
+        fs::write(
+            dir.path().join("version.txt"),
+            "\n  1.2.3  \n\nAdds dosing tables\n# notes for maintainers\n",
+        )
+        .unwrap();
+
+        assert_eq!(read(&file_manager).unwrap(), Version::new(1, 2, 3));
+        assert_eq!(
+            read_summary(&file_manager).unwrap().as_deref(),
+            Some("Adds dosing tables")
+        );
+    }
+
+    #[test]
+    fn version_file_without_summary() {
+        let (dir, file_manager) = vault(serde_json::json!({}));
+        fs::write(dir.path().join("version.txt"), "1.2.3\n\n\n").unwrap();
+
+        assert_eq!(read(&file_manager).unwrap(), Version::new(1, 2, 3));
+        assert_eq!(read_summary(&file_manager).unwrap(), None);
+    }
+
+    #[test]
+    fn version_must_be_on_the_first_non_empty_line() {
+        let (dir, file_manager) = vault(serde_json::json!({}));
+        fs::write(dir.path().join("version.txt"), "# release\n1.2.3\n").unwrap();
+
+        let error = read_text(&file_manager, "version.txt").unwrap_err();
+        assert_eq!(error.to_string(), "Failed to parse version in version.txt");
+    }
+}
@@ -0,0 +1,97 @@
+/// Watch mode
+///
+/// Watches the `pages/` directory and triggers an action once a burst of
+/// changes has settled. Editors that save atomically (write a temporary file
+/// and rename it over the page) surface as create/remove/rename events, so
+/// every event kind touching a `.md` file counts as a change.
+///
+/// A failing action is reported and watching goes on, so a transient error,
+/// such as a page caught mid-save, doesn't end it.
+use anyhow::{Context, Result};
+use notify::{Event, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+
+use crate::file_manager::FileManager;
+
+/// Default time to wait for further events before triggering
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Interval for checking whether Ctrl-C was pressed
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Watch the pages directory until Ctrl-C is pressed
+///
+/// Calls `on_change` once per burst of changes, after no further change
+/// arrived for `debounce`. Events touching only the pages that `ignored`
+/// returns are skipped, so writes made by `on_change` itself do not retrigger
+/// it. `ignored` is called for every event, as the pages may change.
+pub fn watch(
+    file_manager: &FileManager,
+    ignored: impl Fn() -> Vec<String>,
+    debounce: Duration,
+    mut on_change: impl FnMut() -> Result<()>,
+) -> Result<()> {
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_running = running.clone();
+    ctrlc::set_handler(move || handler_running.store(false, Ordering::SeqCst))
+        .context("Failed to set Ctrl-C handler")?;
+
+    let pages_dir = file_manager.pages_dir();
+    let ignored_paths = || {
+        ignored()
+            .iter()
+            .map(|title| pages_dir.join(title.replace("/", "___") + ".md"))
+            .collect::<Vec<_>>()
+    };
+
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to create watcher")?;
+    watcher
+        .watch(&pages_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", pages_dir.display()))?;
+    println!("Watching {} (Ctrl-C to stop)", pages_dir.display());
+
+    while running.load(Ordering::SeqCst) {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(event) if is_page_change(&event, &ignored_paths()) => {
+                // Coalesce the rest of the burst
+                while rx.recv_timeout(debounce).is_ok() {}
+                if let Err(e) = on_change() {
+                    eprintln!("Error: {:#}", e);
+                }
+            }
+            Ok(Err(e)) => eprintln!("Warning: {}", e),
+            Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    println!("Stopped watching.");
+    Ok(())
+}
+
+/// Check whether an event touches a page that isn't ignored
+fn is_page_change(event: &notify::Result<Event>, ignored: &[PathBuf]) -> bool {
+    let Ok(event) = event else {
+        return false;
+    };
+    if event.kind.is_access() {
+        return false;
+    }
+    event
+        .paths
+        .iter()
+        .any(|path| is_page_path(path) && !ignored.iter().any(|ignored| ignored == path))
+}
+
+/// Check whether a path looks like a page file
+fn is_page_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md")
+}
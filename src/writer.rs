@@ -0,0 +1,89 @@
+/// Changes to the vault
+///
+/// `FileManager` makes every change to disk and to the git repository
+/// through a `Writer`. `Disk` makes the changes, while `DryRun` only prints
+/// them, so the global `--dry-run` previews every command the same way.
+use anyhow::{Context, Result};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Destination of the changes made by commands
+pub trait Writer: fmt::Debug + Send + Sync {
+    /// Write a file, creating its parent directories if needed
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()>;
+
+    /// Move a file
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// Run an operation that isn't a file write, such as a git commit
+    ///
+    /// `description` completes "Would ..." when the operation is skipped.
+    fn run(&self, description: &str, operation: &mut dyn FnMut() -> Result<()>) -> Result<()>;
+}
+
+/// Writer making the changes
+#[derive(Debug)]
+pub struct Disk;
+
+impl Writer for Disk {
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to).with_context(|| format!("Failed to move {}", from.display()))
+    }
+
+    fn run(&self, _description: &str, operation: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        operation()
+    }
+}
+
+/// Writer printing the changes instead of making them
+///
+/// Paths under the project root are printed relative to it.
+#[derive(Debug)]
+pub struct DryRun {
+    root: PathBuf,
+}
+
+impl DryRun {
+    /// Create a dry-run writer for the project at `root`
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Shorten a path to be relative to the project root, if it's inside it
+    fn display<'a>(&self, path: &'a Path) -> std::path::Display<'a> {
+        path.strip_prefix(&self.root).unwrap_or(path).display()
+    }
+}
+
+impl Writer for DryRun {
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        println!(
+            "Would write {} ({} bytes)",
+            self.display(path),
+            contents.len()
+        );
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        println!("Would move {} -> {}", self.display(from), self.display(to));
+        Ok(())
+    }
+
+    fn run(&self, description: &str, _operation: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        println!("Would {}", description);
+        Ok(())
+    }
+}